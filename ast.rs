@@ -0,0 +1,168 @@
+use crate::token::Span;
+
+/// A whole `.lol` file: `#HAI ... #KTHXBYE`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+    /// Comments between `#HAI` and the head (emitted before `<head>`).
+    pub prelude: Vec<Node>,
+    pub head: Option<Head>,
+    pub body: Vec<Node>,
+    /// `#GIMMEH FUTNOTE` contents in order of appearance; note `n` is at index `n - 1`.
+    pub footnotes: Vec<Footnote>,
+    pub span: Span,
+}
+
+/// The content of one footnote, referenced in place by `NodeKind::FootnoteRef`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Footnote {
+    pub number: usize,
+    pub children: Vec<Node>,
+    pub span: Span,
+    /// The included file the footnote was written in; `None` for the main file.
+    pub file: Option<String>,
+}
+
+/// `#MAEK HEAD ... #OIC`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Head {
+    pub title: Title,
+    pub span: Span,
+}
+
+/// `#GIMMEH TITLE ... #MKAY`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Title {
+    pub text: String,
+    pub span: Span,
+}
+
+/// Inline styles beyond bold and italics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    Underline,
+    Strike,
+    Superscript,
+    Subscript,
+    Highlight,
+}
+
+/// One heading as listed in a table of contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    pub id: String,
+}
+
+/// How a numbered list counts its items.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberStyle {
+    #[default]
+    Decimal,
+    LowerRoman,
+    UpperRoman,
+    LowerAlpha,
+    UpperAlpha,
+}
+
+/// Any construct that can appear in the body, a paragraph or a list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub kind: NodeKind,
+    /// From the introducing tag (`#MAEK`, `#GIMMEH`, ...) through its closer.
+    pub span: Span,
+    /// Set by a `#GIMMEH ANKR <id> #MKAY` right before the block.
+    pub anchor: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind {
+    /// `#MAEK PARAGRAF ... #OIC`
+    Paragraph(Vec<Node>),
+    /// `#MAEK SECSHUN [id] [#IT IZ heading #MKAY] ... #OIC`; body content,
+    /// led by a `Heading` child when a heading was given.
+    Section { id: Option<String>, children: Vec<Node> },
+    /// `#GIMMEH FILEZ <path> #MKAY`: the body content of another file,
+    /// whose spans refer to `file`.
+    Include { file: String, children: Vec<Node> },
+    /// `#GIMMEH LYNE #MKAY`
+    Rule,
+    /// `#MAEK QUOTEZ ... #OIC` with paragraph content; at most one child is a `Cite`.
+    Quote(Vec<Node>),
+    /// `#GIMMEH CITE ... #MKAY`, the attribution of the enclosing quote.
+    Cite(Vec<Node>),
+    /// `#MAEK LIST ... #OIC`; children are items, newlines and comments.
+    List(Vec<Node>),
+    /// `#MAEK NUMBRD LIST [start] [style] ... #OIC`; children as for `List`.
+    NumberedList { start: Option<u32>, style: NumberStyle, children: Vec<Node> },
+    /// `#GIMMEH ITEM ... #MKAY` with rich inline content and nested lists.
+    Item(Vec<Node>),
+    /// `#MAEK TABL ... #OIC`; children are rows and comments, header rows first.
+    Table(Vec<Node>),
+    /// `#MAEK ROW ... #OIC`, or `#MAEK HEADR ROW ... #OIC` when `header`.
+    Row { header: bool, cells: Vec<Node> },
+    /// `#GIMMEH CELL ... #MKAY` with rich inline content.
+    Cell(Vec<Node>),
+    /// `#MAEK KODE [lang]` then literal lines up to `#OIC` on its own line.
+    CodeBlock { lang: Option<String>, code: String },
+    /// `#GIMMEH HEADR <level> ... #MKAY`, level 1-6. `id` is filled in by
+    /// `outline::resolve`.
+    Heading { level: u8, text: String, id: Option<String> },
+    /// `#GIMMEH TOC #MKAY`; `outline::resolve` fills in every heading of the document.
+    Toc(Vec<TocEntry>),
+    /// `#GIMMEH LINKZ <url> #IT IZ <rich text> #MKAY`
+    Link { href: String, children: Vec<Node> },
+    /// `#GIMMEH PIKCHUR <src> [WxH] #IT IZ <alt> #MKAY`
+    Image { src: String, alt: String, width: Option<u32>, height: Option<u32> },
+    /// `#GIMMEH BOLD ... #MKAY` with rich inline content.
+    Bold(Vec<Node>),
+    /// `#GIMMEH ITALICS ... #MKAY` with rich inline content.
+    Italics(Vec<Node>),
+    /// `#GIMMEH REFZ <anchor> #MKAY`; `outline::resolve` fills in the
+    /// target's heading text or number.
+    CrossRef { target: String, text: Option<String> },
+    /// Where `#GIMMEH FUTNOTE ... #MKAY` stood; the content is in `Document::footnotes`.
+    FootnoteRef(usize),
+    /// `#GIMMEH KODE ... #MKAY`, taken verbatim.
+    Code(String),
+    /// `#GIMMEH UNDERLIN|STRIKE|SUPR|SUBZ|HILITE ... #MKAY` with rich inline content.
+    Styled { style: TextStyle, children: Vec<Node> },
+    Newline,
+    /// `#GIMMEH SOUNDZ <src> #MKAY`
+    Audio(String),
+    /// `#GIMMEH VIDZ <src> #MKAY`
+    Video(String),
+    /// `#I HAZ name #IT IZ value #MKAY`; produces no output.
+    VarDefine { name: String, value: String },
+    /// `#LEMME SEE name #MKAY`, with the value it resolved to in scope.
+    VarUse { name: String, value: String },
+    /// `#GIMMEH HTMLZ ... #MKAY`: markup emitted without escaping.
+    Raw(String),
+    /// `#OBTW ... #TLDR`
+    Comment(String),
+    Text(String),
+}
+
+impl NodeKind {
+    /// Child nodes of composite constructs, for passes that walk the tree.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<Node>> {
+        match self {
+            NodeKind::Paragraph(children)
+            | NodeKind::Section { children, .. }
+            | NodeKind::Include { children, .. }
+            | NodeKind::Quote(children)
+            | NodeKind::Cite(children)
+            | NodeKind::List(children)
+            | NodeKind::NumberedList { children, .. }
+            | NodeKind::Item(children)
+            | NodeKind::Table(children)
+            | NodeKind::Row { cells: children, .. }
+            | NodeKind::Cell(children)
+            | NodeKind::Link { children, .. }
+            | NodeKind::Bold(children)
+            | NodeKind::Italics(children)
+            | NodeKind::Styled { children, .. } => Some(children),
+            _ => None,
+        }
+    }
+}
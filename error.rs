@@ -0,0 +1,218 @@
+use std::fmt;
+
+use crate::token::Span;
+
+/// Which phase of the compiler rejected the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Lexical,
+    Syntax,
+    Semantic,
+}
+
+/// A compile failure returned to the caller instead of exiting the process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Source file the error was found in, when known.
+    pub file: Option<String>,
+    /// Location of the offending lexeme, when known.
+    pub span: Option<Span>,
+    /// Short text printed under the caret in rendered output.
+    pub label: Option<String>,
+    /// Suggestion printed as a trailing `= help:` line.
+    pub help: Option<String>,
+    /// Secondary locations (e.g. the opener of an unclosed block) with their labels.
+    pub related: Vec<(Span, String)>,
+}
+
+/// A failed compilation: the errors reported, in source order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub errors: Vec<CompileError>,
+    /// `error_limit` was reached with more errors left unreported.
+    pub aborted: bool,
+}
+
+impl CompileError {
+    pub fn new<S: Into<String>>(kind: ErrorKind, message: S) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            file: None,
+            span: None,
+            label: None,
+            help: None,
+            related: Vec::new(),
+        }
+    }
+
+    pub fn with_label<S: Into<String>>(mut self, label: S) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_help<S: Into<String>>(mut self, help: S) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Point at another place in the source that explains this error.
+    pub fn with_related<S: Into<String>>(mut self, span: Span, label: S) -> Self {
+        self.related.push((span, label.into()));
+        self
+    }
+
+    /// Attach a location unless one was already recorded.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    /// Attach the source file name unless one was already recorded.
+    pub fn in_file(mut self, file: &str) -> Self {
+        if self.file.is_none() && !file.is_empty() {
+            self.file = Some(file.to_string());
+        }
+        self
+    }
+
+    pub fn lexical<S: Into<String>>(message: S) -> Self { Self::new(ErrorKind::Lexical, message) }
+    pub fn syntax<S: Into<String>>(message: S) -> Self { Self::new(ErrorKind::Syntax, message) }
+    pub fn semantic<S: Into<String>>(message: S) -> Self { Self::new(ErrorKind::Semantic, message) }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::Lexical => "Lexical error",
+            ErrorKind::Syntax => "Syntax error",
+            ErrorKind::Semantic => "Static semantic error",
+        })
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        if let Some(span) = &self.span {
+            write!(f, "{}:{}:", span.line, span.column)?;
+        }
+        if self.file.is_some() || self.span.is_some() {
+            f.write_str(" ")?;
+        }
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/* ---------- rustc-style rendering ---------- */
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+fn paint(text: &str, style: &str, color: bool) -> String {
+    if color { format!("{}{}{}", style, text, RESET) } else { text.to_string() }
+}
+
+/// Tabs are shown as four spaces so carets line up with what the user sees.
+fn expand_tabs(s: &str) -> String {
+    s.replace('\t', "    ")
+}
+
+impl CompileError {
+    /// Render this error the way rustc does: a header, the `file:line:col`
+    /// pointer, each involved source line with an underline, and a help note.
+    /// `source` must be the text the error's spans were taken from.
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let mut out = format!(
+            "{}{}\n",
+            paint(&format!("{}:", self.kind), RED, color),
+            paint(&format!(" {}", self.message), BOLD, color),
+        );
+
+        let Some(primary) = self.span else {
+            if let Some(file) = &self.file {
+                out.push_str(&format!("{} {}\n", paint("-->", BLUE, color), file));
+            }
+            if let Some(help) = &self.help {
+                out.push_str(&format!("{} help: {}\n", paint("=", BLUE, color), help));
+            }
+            return out;
+        };
+
+        // Primary span first, then related ones; printed in source order.
+        let mut marks: Vec<(Span, Option<&str>, bool)> = vec![(primary, self.label.as_deref(), true)];
+        for (span, label) in &self.related {
+            marks.push((*span, Some(label.as_str()), false));
+        }
+        marks.sort_by_key(|(span, _, _)| (span.line, span.column));
+
+        let width = marks.iter().map(|(s, _, _)| s.line).max().unwrap_or(1).to_string().len();
+        let gutter = " ".repeat(width);
+        let bar = paint("|", BLUE, color);
+
+        out.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            paint("-->", BLUE, color),
+            self.file.as_deref().unwrap_or("<input>"),
+            primary.line,
+            primary.column
+        ));
+        out.push_str(&format!("{} {}\n", gutter, bar));
+
+        let mut last_line = None;
+        for (span, label, is_primary) in marks {
+            let line_text = source.lines().nth(span.line.saturating_sub(1)).unwrap_or("");
+            if let Some(prev) = last_line
+                && span.line > prev + 1
+            {
+                out.push_str(&paint("...", BLUE, color));
+                out.push('\n');
+            }
+            if last_line != Some(span.line) {
+                out.push_str(&format!(
+                    "{} {} {}\n",
+                    paint(&format!("{:>width$}", span.line), BLUE, color),
+                    bar,
+                    expand_tabs(line_text)
+                ));
+            }
+            last_line = Some(span.line);
+
+            // Underline from the span's column to its end (or the end of the line).
+            let before: String = line_text.chars().take(span.column.saturating_sub(1)).collect();
+            let len = source
+                .get(span.start..span.end)
+                .map(|s| s.split('\n').next().unwrap_or("").chars().count())
+                .unwrap_or(0)
+                .max(1);
+            let (mark, style) = if is_primary { ("^", RED) } else { ("-", BLUE) };
+            let mut underline = mark.repeat(len);
+            if let Some(label) = label {
+                underline.push(' ');
+                underline.push_str(label);
+            }
+            out.push_str(&format!(
+                "{} {} {}{}\n",
+                gutter,
+                bar,
+                " ".repeat(expand_tabs(&before).chars().count()),
+                paint(&underline, style, color)
+            ));
+        }
+
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{} {}\n", gutter, bar));
+            out.push_str(&format!("{} {} help: {}\n", gutter, paint("=", BLUE, color), help));
+        }
+        out
+    }
+}
@@ -1,3 +1,5 @@
+use crate::ast::{Document, Footnote, Node, NodeKind, NumberStyle, TextStyle, TocEntry};
+
 #[derive(Default)]
 pub struct Html {
     parts: Vec<String>,
@@ -6,6 +8,8 @@ pub struct Html {
     // When true: free text is emitted one word per line (matches prof's samples).
     // We disable this inside <p>…</p> so your paragraph output stays compact.
     word_per_line: bool,
+    // Rendered footnotes section, placed before </body> by finish().
+    notes: Vec<String>,
 }
 
 impl Html {
@@ -25,7 +29,7 @@ impl Html {
     pub fn end_head(&mut self) { self.push("</head>"); }
 
     pub fn title(&mut self, t: &str) {
-        self.push(format!("<title>{}</title>", t.trim()));
+        self.push(format!("<title>{}</title>", escape_text(t.trim())));
     }
 
     pub fn begin_body(&mut self) {
@@ -43,12 +47,12 @@ impl Html {
     }
 
     pub fn comment(&mut self, t: &str) {
-        self.push(format!("<!-- {} -->", t.trim()));
+        self.push(format!("<!-- {} -->", escape_comment(t.trim())));
     }
 
-    pub fn begin_p(&mut self) {
+    pub fn begin_p(&mut self, id: Option<&str>) {
         self.begin_body();              // ensure we're in <body>
-        self.push("<p>");
+        self.push(format!("<p{}>", id_attr(id)));
         self.word_per_line = false;     // paragraphs should not be word-per-line
     }
     pub fn end_p(&mut self) {
@@ -61,32 +65,114 @@ impl Html {
         self.push("<br>");
     }
 
-    pub fn bold(&mut self, t: &str) {
+    /// `inner` is already-rendered markup.
+    pub fn bold(&mut self, inner: &str) {
+        self.begin_body();
+        self.push(format!("<b>{}</b>", inner));
+    }
+    pub fn italics(&mut self, inner: &str) {
         self.begin_body();
-        self.push(format!("<b>{}</b>", t.trim()));
+        self.push(format!("<i>{}</i>", inner));
     }
-    pub fn italics(&mut self, t: &str) {
+
+    /// `<hN id="...">`; ids are assigned by `outline::resolve`.
+    pub fn heading(&mut self, level: u8, id: Option<&str>, t: &str) {
         self.begin_body();
-        self.push(format!("<i>{}</i>", t.trim()));
+        match id {
+            Some(id) => self.push(format!("<h{0} id=\"{1}\">{2}</h{0}>", level, escape_attr(id), escape_text(t.trim()))),
+            None => self.push(format!("<h{0}>{1}</h{0}>", level, escape_text(t.trim()))),
+        }
+    }
+
+    /// `<nav>` with nested `<ol>`s following the heading levels.
+    pub fn toc(&mut self, entries: &[TocEntry]) {
+        self.begin_body();
+        self.push("<nav class=\"toc\">");
+        let mut open: Vec<u8> = Vec::new(); // level of each open <ol>
+        for entry in entries {
+            match open.last() {
+                None => {
+                    self.push("<ol>");
+                    open.push(entry.level);
+                }
+                // A deeper heading nests inside the previous entry's <li>.
+                Some(&last) if entry.level > last => {
+                    self.push("<ol>");
+                    open.push(entry.level);
+                }
+                Some(_) => {
+                    self.push("</li>");
+                    while open.len() > 1 && open.last().is_some_and(|&l| entry.level < l) {
+                        self.push("</ol>");
+                        self.push("</li>");
+                        open.pop();
+                    }
+                }
+            }
+            self.push(format!(
+                "<li><a href=\"#{}\">{}</a>",
+                escape_attr(&entry.id),
+                escape_text(entry.text.trim())
+            ));
+        }
+        if !open.is_empty() {
+            self.push("</li>");
+        }
+        while open.pop().is_some() {
+            self.push("</ol>");
+            if !open.is_empty() {
+                self.push("</li>");
+            }
+        }
+        self.push("</nav>");
+    }
+
+    /// Author-supplied markup, passed through untouched (`#GIMMEH HTMLZ`).
+    pub fn raw(&mut self, t: &str) {
+        self.begin_body();
+        self.push(t.trim());
     }
 
     pub fn text(&mut self, t: &str) {
         self.begin_body();
-        let trimmed = t.replace('\r', " ").replace('\n', " ");
+        let trimmed = t.replace(['\r', '\n'], " ");
         if trimmed.trim().is_empty() { return; }
 
         if self.word_per_line {
             // Emit each word on its own source line (browser still renders inline).
             for w in trimmed.split_whitespace() {
-                self.push(w.to_string());
+                self.push(escape_text(w));
             }
         } else {
             // Inside <p>…</p> keep it compact
-            self.push(trimmed.trim().to_string());
+            self.push(escape_text(trimmed.trim()));
         }
     }
 
+    /// Render the footnotes list; `finish` puts it at the end of the body.
+    pub fn footnotes(&mut self, notes: &[Footnote]) {
+        if notes.is_empty() {
+            return;
+        }
+        let mut section = vec!["<section class=\"footnotes\">".to_string(), "<ol>".to_string()];
+        for note in notes {
+            let text = self.inline(&note.children);
+            section.push(format!(
+                "<li id=\"fn-{0}\">{1} <a href=\"#fnref-{0}\">&#8617;</a></li>",
+                note.number, text
+            ));
+        }
+        section.push("</ol>".to_string());
+        section.push("</section>".to_string());
+        self.notes = section;
+    }
+
     pub fn finish(mut self) -> String {
+        if !self.notes.is_empty() {
+            let notes = std::mem::take(&mut self.notes);
+            let at = self.parts.iter().position(|s| s == "</body>").unwrap_or(self.parts.len());
+            self.parts.splice(at..at, notes);
+        }
         // If no explicit <body> was opened, open & close it so HTML is valid.
         if !self.in_body {
             // Only create an empty body if nothing else already did.
@@ -96,7 +182,7 @@ impl Html {
             self.push("</body>");
         } else {
             // Ensure body is closed if user code forgot.
-            if !self.parts.last().map_or(false, |s| s == "</body>") {
+            if self.parts.last().is_none_or(|s| s != "</body>") {
                 self.push("</body>");
             }
         }
@@ -104,3 +190,373 @@ impl Html {
         self.parts.join("\n")
     }
 }
+
+/* ---------- tree walk ---------- */
+
+/// Emit the HTML for a parsed document.
+pub fn render(doc: &Document) -> String {
+    let mut html = Html::default();
+    // <!doctype html><html>
+    html.begin_html();
+    for node in &doc.prelude {
+        html.node(node);
+    }
+    if let Some(head) = &doc.head {
+        html.begin_head();
+        html.title(&head.title.text);
+        html.end_head();
+    }
+
+    // Ensure <body> is open so plain text lands inside it
+    html.begin_body();
+    for node in &doc.body {
+        html.node(node);
+    }
+    html.footnotes(&doc.footnotes);
+
+    // Close </body> and then </html>
+    html.end_body();
+
+    // NOTE:
+    // If your htmlgen.finish() already appends </html>, you can remove the next line.
+    // Keep it ONLY if your Html.finish() does NOT auto-close.
+    html.end_html();
+    html.finish()
+}
+
+impl Html {
+    /// Inline nodes written with no whitespace between them in the source
+    /// (`H#GIMMEH SUBZ 2 #MKAY O`) are rendered with none between them either.
+    fn nodes(&mut self, nodes: &[Node]) {
+        let mut prev: Option<&Node> = None;
+        for node in nodes {
+            let before = self.parts.len();
+            self.node(node);
+            let touching = prev.is_some_and(|p| is_inline(p) && is_inline(node) && p.span.end == node.span.start);
+            if touching && before > 0 && self.parts.len() > before {
+                let first = self.parts.remove(before);
+                self.parts[before - 1].push_str(&first);
+            }
+            prev = Some(node);
+        }
+    }
+
+    /// Render `nodes` on their own and return them joined on one line,
+    /// for content that sits inside a single inline element.
+    fn inline(&mut self, nodes: &[Node]) -> String {
+        let outer = std::mem::take(&mut self.parts);
+        let word_per_line = std::mem::replace(&mut self.word_per_line, false);
+        self.nodes(nodes);
+        self.word_per_line = word_per_line;
+        std::mem::replace(&mut self.parts, outer).join(" ")
+    }
+
+    /// `id` goes on the first element emitted, a `<p>` or a list.
+    fn paragraph(&mut self, children: &[Node], mut id: Option<&str>) {
+        if !children.iter().any(is_list) {
+            self.begin_p(id);
+            self.nodes(children);
+            self.end_p();
+            return;
+        }
+        // <ul>/<ol> cannot live inside <p>: close the paragraph before
+        // each list and reopen it for whatever follows.
+        let mut rest = children;
+        while !rest.is_empty() {
+            let run = rest.iter().position(is_list).unwrap_or(rest.len());
+            if run > 0 {
+                self.begin_p(id.take());
+                self.nodes(&rest[..run]);
+                self.end_p();
+            }
+            let Some(list) = rest.get(run) else { break };
+            // Lists inside a paragraph cannot carry their own anchor.
+            match id.take() {
+                Some(id) => self.node(&Node { anchor: Some(id.to_string()), ..list.clone() }),
+                None => self.node(list),
+            }
+            rest = &rest[run + 1..];
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match &node.kind {
+            NodeKind::Paragraph(children) => self.paragraph(children, node.anchor.as_deref()),
+            NodeKind::Section { id, children } => {
+                self.begin_body();
+                match id {
+                    Some(id) => self.push(format!("<section id=\"{}\">", escape_attr(id))),
+                    None => self.push("<section>"),
+                }
+                self.nodes(children);
+                self.push("</section>");
+            }
+            NodeKind::Include { children, .. } => self.nodes(children),
+            NodeKind::Rule => {
+                self.begin_body();
+                self.push("<hr>");
+            }
+            NodeKind::Quote(children) => {
+                self.begin_body();
+                let (cite, body): (Vec<Node>, Vec<Node>) =
+                    children.iter().cloned().partition(|c| matches!(c.kind, NodeKind::Cite(_)));
+                if cite.is_empty() {
+                    self.push("<blockquote>");
+                    self.paragraph(&body, None);
+                    self.push("</blockquote>");
+                } else {
+                    // Attribution sits outside the quote itself.
+                    self.push("<figure>");
+                    self.push("<blockquote>");
+                    self.paragraph(&body, None);
+                    self.push("</blockquote>");
+                    self.push("<figcaption>");
+                    self.nodes(&cite);
+                    self.push("</figcaption>");
+                    self.push("</figure>");
+                }
+            }
+            NodeKind::Cite(children) => {
+                let inner = self.inline(children);
+                self.push(format!("<cite>{}</cite>", inner));
+            }
+            NodeKind::List(children) => {
+                self.push(format!("<ul{}>", id_attr(node.anchor.as_deref())));
+                self.nodes(children);
+                self.push("</ul>");
+            }
+            NodeKind::NumberedList { start, style, children } => {
+                let mut tag = format!("<ol{}", id_attr(node.anchor.as_deref()));
+                if let Some(n) = start {
+                    tag.push_str(&format!(" start=\"{}\"", n));
+                }
+                let kind = match style {
+                    NumberStyle::Decimal => None,
+                    NumberStyle::LowerRoman => Some("i"),
+                    NumberStyle::UpperRoman => Some("I"),
+                    NumberStyle::LowerAlpha => Some("a"),
+                    NumberStyle::UpperAlpha => Some("A"),
+                };
+                if let Some(kind) = kind {
+                    tag.push_str(&format!(" type=\"{}\"", kind));
+                }
+                tag.push('>');
+                self.push(tag);
+                self.nodes(children);
+                self.push("</ol>");
+            }
+            NodeKind::Item(children) => {
+                self.push("<li>");
+                self.nodes(children);
+                self.push("</li>");
+            }
+            NodeKind::Table(rows) => {
+                self.begin_body();
+                self.push(format!("<table{}>", id_attr(node.anchor.as_deref())));
+                // Header rows come first (the parser enforces it), so one
+                // pass opens <thead> and switches to <tbody> at the first body row.
+                let mut section: Option<&str> = None;
+                for row in rows {
+                    if let NodeKind::Row { header, .. } = row.kind {
+                        let want = if header { "thead" } else { "tbody" };
+                        if section != Some(want) {
+                            if let Some(open) = section {
+                                self.push(format!("</{}>", open));
+                            }
+                            self.push(format!("<{}>", want));
+                            section = Some(want);
+                        }
+                    }
+                    self.node(row);
+                }
+                if let Some(open) = section {
+                    self.push(format!("</{}>", open));
+                }
+                self.push("</table>");
+            }
+            NodeKind::Row { header, cells } => {
+                let tag = if *header { "th" } else { "td" };
+                self.push("<tr>");
+                for cell in cells {
+                    match &cell.kind {
+                        NodeKind::Cell(children) => {
+                            let content = self.inline(children);
+                            self.push(format!("<{0}>{1}</{0}>", tag, content));
+                        }
+                        _ => self.node(cell),
+                    }
+                }
+                self.push("</tr>");
+            }
+            NodeKind::Cell(children) => {
+                let content = self.inline(children);
+                self.push(format!("<td>{}</td>", content));
+            }
+            NodeKind::CodeBlock { lang, code } => {
+                self.begin_body();
+                let class = match lang {
+                    Some(lang) => format!(" class=\"language-{}\"", escape_attr(lang)),
+                    None => String::new(),
+                };
+                self.push(format!("<pre><code{}>{}</code></pre>", class, escape_text(code)));
+            }
+            NodeKind::Heading { level, text, id } => self.heading(*level, id.as_deref(), text),
+            NodeKind::Toc(entries) => self.toc(entries),
+            NodeKind::Link { href, children } => {
+                self.begin_body();
+                let text = self.inline(children);
+                self.push(format!("<a href=\"{}\">{}</a>", escape_attr(href), text));
+            }
+            NodeKind::Image { src, alt, width, height } => {
+                self.begin_body();
+                let mut tag = format!("<img src=\"{}\" alt=\"{}\"", escape_attr(src), escape_attr(alt));
+                if let Some(w) = width {
+                    tag.push_str(&format!(" width=\"{}\"", w));
+                }
+                if let Some(h) = height {
+                    tag.push_str(&format!(" height=\"{}\"", h));
+                }
+                tag.push('>');
+                self.push(tag);
+            }
+            NodeKind::Bold(children) => {
+                let inner = self.inline(children);
+                self.bold(&inner);
+            }
+            NodeKind::Italics(children) => {
+                let inner = self.inline(children);
+                self.italics(&inner);
+            }
+            NodeKind::CrossRef { target, text } => {
+                self.begin_body();
+                let text = text.as_deref().unwrap_or(target);
+                self.push(format!("<a href=\"#{}\">{}</a>", escape_attr(target), escape_text(text)));
+            }
+            NodeKind::FootnoteRef(n) => {
+                self.begin_body();
+                self.push(format!("<sup id=\"fnref-{0}\"><a href=\"#fn-{0}\">{0}</a></sup>", n));
+            }
+            NodeKind::Code(code) => {
+                self.begin_body();
+                self.push(format!("<code>{}</code>", escape_text(code)));
+            }
+            NodeKind::Styled { style, children } => {
+                let tag = match style {
+                    TextStyle::Underline => "u",
+                    TextStyle::Strike => "s",
+                    TextStyle::Superscript => "sup",
+                    TextStyle::Subscript => "sub",
+                    TextStyle::Highlight => "mark",
+                };
+                self.begin_body();
+                let inner = self.inline(children);
+                self.push(format!("<{0}>{1}</{0}>", tag, inner));
+            }
+            NodeKind::Newline => self.br(),
+            NodeKind::Audio(src) => self.push(format!(
+                "<audio controls><source src=\"{}\" /></audio>",
+                escape_attr(src)
+            )),
+            // Simple iframe; you can style/size later as needed
+            NodeKind::Video(src) => self.push(format!(
+                "<iframe src=\"{}\" allowfullscreen loading=\"lazy\"></iframe>",
+                escape_attr(src)
+            )),
+            NodeKind::VarDefine { .. } => {}
+            NodeKind::VarUse { value, .. } => self.push(escape_text(value)),
+            NodeKind::Raw(markup) => self.raw(markup),
+            NodeKind::Comment(t) => self.comment(t),
+            NodeKind::Text(t) => self.text(t),
+        }
+    }
+}
+
+/// ` id="..."` for an anchored block's opening tag, or nothing.
+fn id_attr(id: Option<&str>) -> String {
+    id.map(|id| format!(" id=\"{}\"", escape_attr(id))).unwrap_or_default()
+}
+
+/// Nodes that sit in running text, as opposed to blocks.
+fn is_inline(node: &Node) -> bool {
+    matches!(
+        node.kind,
+        NodeKind::Text(_)
+            | NodeKind::Bold(_)
+            | NodeKind::Italics(_)
+            | NodeKind::Styled { .. }
+            | NodeKind::Code(_)
+            | NodeKind::Link { .. }
+            | NodeKind::Image { .. }
+            | NodeKind::CrossRef { .. }
+            | NodeKind::FootnoteRef(_)
+            | NodeKind::VarUse { .. }
+    )
+}
+
+fn is_list(node: &Node) -> bool {
+    matches!(node.kind, NodeKind::List(_) | NodeKind::NumberedList { .. })
+}
+
+/// Lowercased letters and digits, with runs of anything else collapsed to `-`.
+pub fn slugify(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        if c.is_alphanumeric() {
+            out.extend(c.to_lowercase());
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    let out = out.trim_end_matches('-');
+    if out.is_empty() { "section".to_string() } else { out.to_string() }
+}
+
+/* ---------- escaping, one function per output context ---------- */
+
+/// Escape text placed between tags.
+pub fn escape_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Make text safe inside `<!-- ... -->`: no `--` (which also rules out
+/// `-->` and `--!>`), and no leading `>`/`->` or trailing `-` that would
+/// fuse with the delimiters.
+pub fn escape_comment(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '-' && out.ends_with('-') {
+            out.push(' ');
+        }
+        out.push(c);
+    }
+    if out.starts_with('>') || out.starts_with("->") {
+        out.insert(0, ' ');
+    }
+    if out.ends_with('-') {
+        out.push(' ');
+    }
+    out.replace("<!", "< !")
+}
+
+/// Escape a value placed inside a double- or single-quoted attribute.
+pub fn escape_attr(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&#39;".to_string(),
+            _ => c.to_string(),
+        })
+        .collect::<String>()
+}
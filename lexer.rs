@@ -1,9 +1,10 @@
-use crate::token::Token;
+use crate::error::CompileError;
+use crate::token::{Span, SpannedToken, Token};
 
 /// Character-by-character lexical analyzer facade (per project spec).
 #[allow(dead_code)]
 pub trait LexicalAnalyzer {
-    fn get_char(&mut self) -> char;
+    fn get_char(&mut self) -> Result<char, CompileError>;
     fn add_char(&mut self, c: char);
     fn lookup(&self, s: &str) -> bool;
 }
@@ -13,6 +14,11 @@ pub struct Lexer {
     chars: Vec<char>,
     pos: usize,
     buf: String, // scratch for current lexeme
+    offset: usize, // byte offset of `pos`
+    line: usize,
+    column: usize,
+    solid_end: usize, // byte offset just past the last non-whitespace char consumed
+    recent: (Token, Token), // the last two tokens lexed, oldest first
 }
 
 impl Lexer {
@@ -23,6 +29,11 @@ impl Lexer {
             chars: input.chars().collect(),
             pos: 0,
             buf: String::new(),
+            offset: 0,
+            line: 1,
+            column: 1,
+            solid_end: 0,
+            recent: (Token::Eof, Token::Eof),
         }
     }
 
@@ -40,9 +51,29 @@ impl Lexer {
         }
         let c = self.chars[self.pos];
         self.pos += 1;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        if !c.is_whitespace() {
+            self.solid_end = self.offset;
+        }
         Some(c)
     }
 
+    /// Zero-width span at the cursor.
+    pub fn here(&self) -> Span {
+        Span { start: self.offset, end: self.offset, line: self.line, column: self.column }
+    }
+
+    /// Span from `start` up to the last non-whitespace char consumed since.
+    fn span_from(&self, start: Span) -> Span {
+        Span { end: self.solid_end.max(start.start), ..start }
+    }
+
     fn skip_ws(&mut self) {
         while let Some(c) = self.peek() {
             if matches!(c, ' ' | '\t' | '\n' | '\r') {
@@ -54,20 +85,34 @@ impl Lexer {
     }
 
     /// Uppercased lookahead for tag detection without consuming.
-    #[allow(dead_code)]
     fn lookahead_upper(&self, n: usize) -> String {
         let mut s: String = self.chars.iter().skip(self.pos).take(n).collect();
         s.make_ascii_uppercase();
         s
     }
 
+    /// Is the cursor on an escaped `#` (`##` or `\#`), i.e. a literal `#` in text?
+    fn at_escaped_hash(&self) -> bool {
+        matches!(self.peek(), Some('#' | '\\')) && self.chars.get(self.pos + 1) == Some(&'#')
+    }
+
+    /// Consume an escaped `#` and return the literal it stands for.
+    fn read_escaped_hash(&mut self) -> char {
+        self.advance();
+        self.advance();
+        '#'
+    }
+
     /* ---------- lexeme readers ---------- */
 
     /// Read a bare “word” used for keywords/identifiers/URLs.
     fn read_word(&mut self) -> String {
         self.buf.clear();
         while let Some(c) = self.peek() {
-            if c.is_alphanumeric() || matches!(c, '/' | ':' | '.' | '_') {
+            if self.at_escaped_hash() {
+                let c = self.read_escaped_hash();
+                self.buf.push(c);
+            } else if c.is_alphanumeric() || matches!(c, '/' | ':' | '.' | '_') {
                 self.buf.push(c);
                 self.advance();
             } else {
@@ -103,6 +148,10 @@ impl Lexer {
                 self.advance();
                 continue;
             }
+            if self.at_escaped_hash() {
+                out.push(self.read_escaped_hash());
+                continue;
+            }
             if c == '#' {
                 // Stop here; let next_token() lex the tag (#MKAY, #KTHXBYE, #LEMME SEE, etc.)
                 break;
@@ -113,14 +162,100 @@ impl Lexer {
         out.trim().to_string()
     }
 
+    /* ---------- verbatim blocks ---------- */
+
+    /// The rest of the current line (including its newline), trimmed,
+    /// with the span of the trimmed text.
+    pub fn read_line_rest(&mut self) -> (String, Span) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.advance();
+        }
+        let start = self.here();
+        let mut out = String::new();
+        while let Some(c) = self.advance() {
+            if c == '\n' {
+                break;
+            }
+            out.push(c);
+        }
+        let out = out.trim_end().to_string();
+        let span = Span { end: start.start + out.len(), ..start };
+        (out, span)
+    }
+
+    /// Take lines literally, whitespace and `#` included, up to a line
+    /// whose first non-blank text is the tag `close`, which is consumed too.
+    /// `None` if the input ends before the closing line.
+    pub fn read_verbatim(&mut self, close: &str) -> Option<String> {
+        let mut out = String::new();
+        loop {
+            let line_start = self.pos;
+            while matches!(self.peek(), Some(' ' | '\t')) {
+                self.advance();
+            }
+            if self.eat_tag_literal(close) {
+                let out = out.replace("\r\n", "\n");
+                return Some(out.strip_suffix('\n').unwrap_or(&out).to_string());
+            }
+            out.extend(&self.chars[line_start..self.pos]);
+            loop {
+                let c = self.advance()?;
+                out.push(c);
+                if c == '\n' {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Take everything literally up to the tag `close`, which is consumed
+    /// too. `None` if the input ends first.
+    pub fn read_verbatim_until(&mut self, close: &str) -> Option<String> {
+        let mut out = String::new();
+        while !self.eat_tag_literal(close) {
+            out.push(self.advance()?);
+        }
+        Some(out)
+    }
+
+    /// Consume `tag` (ASCII case-insensitive) if the cursor is on it and it
+    /// is not just the start of a longer word.
+    fn eat_tag_literal(&mut self, tag: &str) -> bool {
+        let n = tag.chars().count();
+        let after = self.chars.get(self.pos + n).copied();
+        if self.lookahead_upper(n) != tag || after.is_some_and(char::is_alphabetic) {
+            return false;
+        }
+        for _ in 0..n {
+            self.advance();
+        }
+        true
+    }
+
     /* ---------- public tokenization ---------- */
 
-    pub fn next_token(&mut self) -> Token {
+    /// Lex the next token and record where it came from.
+    pub fn next_token(&mut self) -> Result<SpannedToken, CompileError> {
         self.skip_ws();
-        let Some(c0) = self.peek() else { return Token::Eof; };
+        let start = self.here();
+        let token = self.lex_token().map_err(|e| e.with_span(self.span_from(start)))?;
+        self.recent = (std::mem::replace(&mut self.recent.1, token.clone()), token.clone());
+        Ok(SpannedToken { token, span: self.span_from(start) })
+    }
 
-        // TAGS that start with '#'
-        if c0 == '#' {
+    /// The token `next_token` would return, without consuming it.
+    pub fn peek_token(&mut self) -> Result<SpannedToken, CompileError> {
+        let saved = (self.pos, self.offset, self.line, self.column, self.solid_end, self.recent.clone());
+        let next = self.next_token();
+        (self.pos, self.offset, self.line, self.column, self.solid_end, self.recent) = saved;
+        next
+    }
+
+    fn lex_token(&mut self) -> Result<Token, CompileError> {
+        let Some(c0) = self.peek() else { return Ok(Token::Eof); };
+
+        // TAGS that start with '#' (`##` is a literal '#', lexed as text below)
+        if c0 == '#' && !self.at_escaped_hash() {
             self.advance(); // consume '#'
 
             // Read the first tag word (letters only)
@@ -130,27 +265,30 @@ impl Lexer {
             if w1 == "I" {
                 self.skip_ws();
                 let w2 = self.read_tag_word_upper();
-                if w2 == "HAZ" { return Token::HashIHaz; }
-                eprintln!("Lexical error: expected 'HAZ' after '#I'.");
-                std::process::exit(1);
+                if w2 == "HAZ" { return Ok(Token::HashIHaz); }
+                return Err(CompileError::lexical("expected 'HAZ' after '#I'.")
+                    .with_label("incomplete tag")
+                    .with_help("did you mean `#I HAZ`?"));
             }
             if w1 == "IT" {
                 self.skip_ws();
                 let w2 = self.read_tag_word_upper();
-                if w2 == "IZ" { return Token::HashItIz; }
-                eprintln!("Lexical error: expected 'IZ' after '#IT'.");
-                std::process::exit(1);
+                if w2 == "IZ" { return Ok(Token::HashItIz); }
+                return Err(CompileError::lexical("expected 'IZ' after '#IT'.")
+                    .with_label("incomplete tag")
+                    .with_help("did you mean `#IT IZ`?"));
             }
             if w1 == "LEMME" {
                 self.skip_ws();
                 let w2 = self.read_tag_word_upper();
-                if w2 == "SEE" { return Token::HashLemmeSee; }
-                eprintln!("Lexical error: expected 'SEE' after '#LEMME'.");
-                std::process::exit(1);
+                if w2 == "SEE" { return Ok(Token::HashLemmeSee); }
+                return Err(CompileError::lexical("expected 'SEE' after '#LEMME'.")
+                    .with_label("incomplete tag")
+                    .with_help("did you mean `#LEMME SEE`?"));
             }
 
             // Single-word tags
-            return match w1.as_str() {
+            return Ok(match w1.as_str() {
                 "HAI"      => Token::HashHai,
                 "KTHXBYE"  => Token::HashKthxbye,
                 "OBTW"     => Token::HashObtW,
@@ -160,10 +298,14 @@ impl Lexer {
                 "GIMMEH"   => Token::HashGimmeh,
                 "MKAY"     => Token::HashMKay,
                 _ => {
-                    eprintln!("Lexical error: unknown tag '#{}'", w1);
-                    std::process::exit(1);
+                    let err = CompileError::lexical(format!("unknown tag '#{}'", w1))
+                        .with_label("unknown tag");
+                    return Err(match suggest_tag(&w1) {
+                        Some(tag) => err.with_help(format!("did you mean `{}`?", tag)),
+                        None => err.with_help("write `##` (or `\\#`) for a literal `#` in text"),
+                    });
                 }
-            };
+            });
         }
 
         // Bare keywords / identifiers
@@ -171,39 +313,118 @@ impl Lexer {
             let w = self.read_word();
             let mut up = w.clone();
             up.make_ascii_uppercase();
-            return match up.as_str() {
+            let keyword = match up.as_str() {
                 "HEAD"     => Token::Head,
                 "TITLE"    => Token::Title,
                 "PARAGRAF" => Token::Paragraf,
                 "BOLD"     => Token::Bold,
                 "ITALICS"  => Token::Italics,
+                "UNDERLIN" => Token::Underlin,
+                "STRIKE"   => Token::Strike,
+                "SUPR"     => Token::Supr,
+                "SUBZ"     => Token::Subz,
+                "HILITE"   => Token::Hilite,
                 "LIST"     => Token::List,
+                "NUMBRD"   => Token::Numbrd,
                 "ITEM"     => Token::Item,
                 "NEWLINE"  => Token::Newline,
                 "SOUNDZ"   => Token::Soundz,
                 "VIDZ"     => Token::Vidz,
-                _ => Token::Text(w),
+                "HTMLZ"    => Token::Htmlz,
+                "HEADR"    => Token::Headr,
+                "LINKZ"    => Token::Linkz,
+                "PIKCHUR"  => Token::Pikchur,
+                "TABL"     => Token::Tabl,
+                "ROW"      => Token::Row,
+                "CELL"     => Token::Cell,
+                "KODE"     => Token::Kode,
+                "QUOTEZ"   => Token::Quotez,
+                "CITE"     => Token::Cite,
+                "LYNE"     => Token::Lyne,
+                "SECSHUN"  => Token::Secshun,
+                "FUTNOTE"  => Token::Futnote,
+                "TOC"      => Token::Toc,
+                "ANKR"     => Token::Ankr,
+                "REFZ"     => Token::Refz,
+                "FILEZ"    => Token::Filez,
+                _ => return Ok(Token::Text(w)),
             };
+            // Anywhere else the word is just prose ("the front row").
+            return Ok(if self.keyword_allowed(&keyword) { keyword } else { Token::Text(w) });
         }
 
         // Otherwise: free text until next control
         let text = self.read_until_mkay_or_eol();
         if text.is_empty() {
-            Token::Eof
+            Ok(Token::Eof)
         } else {
-            Token::Text(text)
+            Ok(Token::Text(text))
+        }
+    }
+
+    /// Bare keywords only mean something right after the tag that
+    /// introduces them, or as the second word of `#MAEK NUMBRD LIST` and
+    /// `#MAEK HEADR ROW`.
+    fn keyword_allowed(&self, keyword: &Token) -> bool {
+        matches!(
+            (&self.recent.0, &self.recent.1, keyword),
+            (_, Token::HashGimmeh | Token::HashMaek, _)
+                | (Token::HashMaek, Token::Numbrd, Token::List)
+                | (Token::HashMaek, Token::Headr, Token::Row)
+        )
+    }
+}
+
+/* ---------- "did you mean" for unknown tags ---------- */
+
+const TAGS: &[&str] = &[
+    "#HAI", "#KTHXBYE", "#OBTW", "#TLDR", "#MAEK", "#OIC", "#GIMMEH", "#MKAY",
+    "#I HAZ", "#IT IZ", "#LEMME SEE",
+];
+
+/// Suggest the closest real tag for a mistyped `#WORD`. Bare keywords
+/// written as tags (`#HEAD`) get pointed at the tag that introduces them.
+fn suggest_tag(word: &str) -> Option<String> {
+    match word {
+        "HEAD" | "PARAGRAF" | "LIST" | "TABL" | "ROW" | "KODE" | "QUOTEZ" | "SECSHUN" => return Some(format!("#MAEK {}", word)),
+        "NUMBRD" => return Some("#MAEK NUMBRD LIST".to_string()),
+        "TITLE" | "BOLD" | "ITALICS" | "UNDERLIN" | "STRIKE" | "SUPR" | "SUBZ" | "HILITE" | "ITEM" | "NEWLINE" | "SOUNDZ" | "VIDZ" | "HTMLZ" | "HEADR" | "LINKZ" | "PIKCHUR" | "CELL" | "CITE" | "LYNE" | "FUTNOTE" | "TOC" | "ANKR" | "REFZ" | "FILEZ" => {
+            return Some(format!("#GIMMEH {}", word));
+        }
+        _ => {}
+    }
+    if word.is_empty() {
+        return None;
+    }
+    TAGS.iter()
+        .map(|tag| (edit_distance(word, &tag[1..].replace(' ', "")), *tag))
+        .filter(|(d, _)| *d <= 2 && *d < word.len())
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, tag)| tag.to_string())
+}
+
+/// Plain Levenshtein distance over chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
         }
+        prev = cur;
     }
+    prev[b.len()]
 }
 
 /* ---------- trait facade impl ---------- */
 
 impl LexicalAnalyzer for Lexer {
-    fn get_char(&mut self) -> char {
-        self.advance().unwrap_or_else(|| {
-            eprintln!("Unexpected end of input while lexing");
-            std::process::exit(1);
-        })
+    fn get_char(&mut self) -> Result<char, CompileError> {
+        let at = self.here();
+        self.advance()
+            .ok_or_else(|| CompileError::lexical("unexpected end of input while lexing").with_span(at))
     }
 
     fn add_char(&mut self, c: char) {
@@ -228,11 +449,34 @@ impl LexicalAnalyzer for Lexer {
                 | "PARAGRAF"
                 | "BOLD"
                 | "ITALICS"
+                | "UNDERLIN"
+                | "STRIKE"
+                | "SUPR"
+                | "SUBZ"
+                | "HILITE"
                 | "LIST"
+                | "NUMBRD"
                 | "ITEM"
                 | "NEWLINE"
                 | "SOUNDZ"
                 | "VIDZ"
+                | "HTMLZ"
+                | "HEADR"
+                | "LINKZ"
+                | "PIKCHUR"
+                | "TABL"
+                | "ROW"
+                | "CELL"
+                | "KODE"
+                | "QUOTEZ"
+                | "CITE"
+                | "LYNE"
+                | "SECSHUN"
+                | "FUTNOTE"
+                | "TOC"
+                | "ANKR"
+                | "REFZ"
+                | "FILEZ"
                 | "#I HAZ"
                 | "#IT IZ"
                 | "#LEMME SEE"
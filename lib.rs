@@ -0,0 +1,59 @@
+//! LOLCODE Markdown compiler: turns `.lol` sources into HTML.
+//!
+//! The CLI in `main.rs` is a thin wrapper around [`compile`]; other tools can
+//! embed the compiler the same way and get every failure in the document
+//! back as a list of [`CompileError`]s.
+
+// Errors carry spans, labels and help text for rendering; they are built once
+// per failure, so their size on the error path is not worth boxing away.
+#![allow(clippy::result_large_err)]
+pub mod ast;
+pub mod error;
+pub mod htmlgen;
+pub mod lexer;
+pub mod outline;
+pub mod parser;
+pub mod scope;
+pub mod token;
+
+pub use error::{CompileError, ErrorKind, Failure};
+
+use std::path::PathBuf;
+
+use parser::FrontEnd;
+
+/// Knobs for a single compilation.
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// Name used when reporting errors (usually the input path).
+    pub source_name: String,
+    /// Stop after this many errors; `None` reports everything.
+    pub error_limit: Option<usize>,
+    /// Directory `#GIMMEH FILEZ` may read from; included paths must be
+    /// relative and stay inside it. `None` (the default) turns includes off,
+    /// so embedding tools never read files they did not opt into.
+    pub include_root: Option<PathBuf>,
+}
+
+/// Parse `source` into a typed document tree for analysis or other backends.
+pub fn parse(source: &str, options: &CompileOptions) -> Result<ast::Document, Vec<CompileError>> {
+    parse_with_status(source, options).map_err(|failure| failure.errors)
+}
+
+/// Like [`parse`], but a failure also says whether `error_limit` left errors unreported.
+pub fn parse_with_status(source: &str, options: &CompileOptions) -> Result<ast::Document, Failure> {
+    FrontEnd::new(source, &options.source_name, options.error_limit)
+        .with_include_root(options.include_root.clone())
+        .parse()
+}
+
+/// Compile LOLCODE Markdown `source` into an HTML document, or return every
+/// lexical, syntax and semantic error found (in source order).
+pub fn compile(source: &str, options: &CompileOptions) -> Result<String, Vec<CompileError>> {
+    compile_with_status(source, options).map_err(|failure| failure.errors)
+}
+
+/// Like [`compile`], but a failure also says whether `error_limit` left errors unreported.
+pub fn compile_with_status(source: &str, options: &CompileOptions) -> Result<String, Failure> {
+    parse_with_status(source, options).map(|doc| htmlgen::render(&doc))
+}
@@ -13,7 +13,13 @@ GIMMEH_TITLE   : '#GIMMEH TITLE';
 MAEK_PARAGRAF  : '#MAEK PARAGRAF';
 GIMMEH_BOLD    : '#GIMMEH BOLD';
 GIMMEH_ITALICS : '#GIMMEH ITALICS';
+GIMMEH_UNDERLIN : '#GIMMEH UNDERLIN';
+GIMMEH_STRIKE  : '#GIMMEH STRIKE';
+GIMMEH_SUPR    : '#GIMMEH SUPR';
+GIMMEH_SUBZ    : '#GIMMEH SUBZ';
+GIMMEH_HILITE  : '#GIMMEH HILITE';
 MAEK_LIST      : '#MAEK LIST';
+MAEK_NUMBRD_LIST : '#MAEK NUMBRD LIST';
 GIMMEH_ITEM    : '#GIMMEH ITEM';
 GIMMEH_SOUNDZ  : '#GIMMEH SOUNDZ';
 GIMMEH_VIDZ    : '#GIMMEH VIDZ';
@@ -21,14 +27,37 @@ I_HAZ          : '#I HAZ';
 IT_IZ          : '#IT IZ';
 LEMME_SEE      : '#LEMME SEE';
 GIMMEH_NEWLINE : '#GIMMEH NEWLINE';
+GIMMEH_HTMLZ   : '#GIMMEH HTMLZ';
+GIMMEH_HEADR   : '#GIMMEH HEADR';
+GIMMEH_LINKZ   : '#GIMMEH LINKZ';
+GIMMEH_PIKCHUR : '#GIMMEH PIKCHUR';
+MAEK_TABL      : '#MAEK TABL';
+MAEK_ROW       : '#MAEK ROW';
+MAEK_HEADR_ROW : '#MAEK HEADR ROW';
+GIMMEH_CELL    : '#GIMMEH CELL';
+MAEK_QUOTEZ    : '#MAEK QUOTEZ';
+MAEK_SECSHUN   : '#MAEK SECSHUN';
+GIMMEH_LYNE    : '#GIMMEH LYNE';
+GIMMEH_FUTNOTE : '#GIMMEH FUTNOTE';
+GIMMEH_TOC     : '#GIMMEH TOC';
+GIMMEH_ANKR    : '#GIMMEH ANKR';
+GIMMEH_REFZ    : '#GIMMEH REFZ';
+GIMMEH_FILEZ   : '#GIMMEH FILEZ';
+GIMMEH_CITE    : '#GIMMEH CITE';
+// Raw mode: everything up to #MKAY, taken literally.
+KODE_SPAN      : '#GIMMEH KODE' .*? '#MKAY' ;
+// Raw mode: the optional language, then every line verbatim until a line
+// that starts with #OIC (the lexer reads this in one go, not as WORDs).
+KODE_BLOCK     : '#MAEK KODE' ~('\n')* '\n' .*? '\n' [ \t]* '#OIC' ;
 
 ID
   : ('A'..'Z' | 'a'..'z' | '_')
     ('A'..'Z' | 'a'..'z' | '0'..'9' | '_')*
   ;
 
+// A literal '#' in text is escaped as '##' or '\#'; a lone '#' starts a tag.
 WORD
-  : (~('#' | '\r' | '\n' | '\t'))+
+  : (~('#' | '\r' | '\n' | '\t') | '##' | '\\#')+
   ;
 
 
@@ -41,20 +70,42 @@ WS
 program          : HAI content KTHXBYE EOF ;
 content          : section* ;
 section          : comment | head | paragraph | list | sound | video
-                 | variable_define | variable_use | newline | text ;
+                 | variable_define | variable_use | newline | raw | heading | link | image
+                 | numbered_list | table | code_block | quote | section_block | rule | toc | anchored | include | text ;
 
 comment          : OBTW text TLDR ;
 head             : MAEK_HEAD title OIC ;
 title            : GIMMEH_TITLE text MKAY ;
 paragraph        : MAEK_PARAGRAF paragraph_content OIC ;
-paragraph_content: (text | bold | italics | list | newline)* ;
-bold             : GIMMEH_BOLD text MKAY ;
-italics          : GIMMEH_ITALICS text MKAY ;
+section_block    : MAEK_SECSHUN text? (IT_IZ text MKAY)? section* OIC ; // id on the opening line
+rule             : GIMMEH_LYNE MKAY ;
+toc              : GIMMEH_TOC MKAY ; // expands to every heading in the document
+anchored         : GIMMEH_ANKR ID MKAY (paragraph | list | numbered_list | heading | table) ;
+cross_ref        : GIMMEH_REFZ ID MKAY ; // ID must name an anchor somewhere in the document
+include          : GIMMEH_FILEZ text MKAY ; // path relative to the including file and inside the include root; its content (section*) shares the scope here; no cycles
+quote            : MAEK_QUOTEZ (paragraph_content | cite)* OIC ; // at most one cite
+cite             : GIMMEH_CITE inline_content MKAY ;
+paragraph_content: (text | bold | italics | inline_style | KODE_SPAN | footnote | cross_ref | list | numbered_list | newline | raw | link | image)* ;
+bold             : GIMMEH_BOLD inline_content MKAY ;
+italics          : GIMMEH_ITALICS inline_content MKAY ;
+footnote         : GIMMEH_FUTNOTE inline_content MKAY ; // not inside a link or another footnote
+inline_style     : (GIMMEH_UNDERLIN | GIMMEH_STRIKE | GIMMEH_SUPR | GIMMEH_SUBZ | GIMMEH_HILITE) inline_content MKAY ;
+inline_content   : (text | bold | italics | inline_style | KODE_SPAN | footnote | cross_ref | newline | variable_use | link)* ;
 list             : MAEK_LIST item+ OIC ;
-item             : GIMMEH_ITEM text MKAY ;
+numbered_list    : MAEK_NUMBRD_LIST text? item+ OIC ; // optional start number and decimal|roman|alpha
+item             : GIMMEH_ITEM (text | list | numbered_list)* MKAY ;
 sound            : GIMMEH_SOUNDZ text MKAY ;
 video            : GIMMEH_VIDZ text MKAY ;
 variable_define  : I_HAZ ID IT_IZ text MKAY ;
 variable_use     : LEMME_SEE ID MKAY ;
 newline          : GIMMEH_NEWLINE ;
+raw              : GIMMEH_HTMLZ text MKAY ; // passed through unescaped
+heading          : GIMMEH_HEADR text MKAY ; // text starts with a level 1-6
+link             : GIMMEH_LINKZ text (IT_IZ (text | bold | italics | newline | variable_use)*)? MKAY ;
+image            : GIMMEH_PIKCHUR text IT_IZ text MKAY ; // url, optional WxH size, then alt text
+code_block       : KODE_BLOCK ;
+table            : MAEK_TABL header_row* row* OIC ; // every row has the same number of cells
+header_row       : MAEK_HEADR_ROW cell* OIC ;
+row              : MAEK_ROW cell* OIC ;
+cell             : GIMMEH_CELL inline_content MKAY ;
 text             : WORD+ ;
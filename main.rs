@@ -1,16 +1,13 @@
 // src/main.rs
-mod token;
-mod lexer;
-mod parser;
-mod htmlgen;
-mod scope;
-
-use parser::FrontEnd;
+use lolcompiler::{compile_with_status, CompileOptions, Failure};
 use std::{
     env, fs,
-    path::PathBuf,
+    io::IsTerminal,
+    path::{Path, PathBuf},
     
 };
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
 
 
 // From the handout’s “Compiler” trait (simplified mapping)
@@ -36,7 +33,6 @@ impl Compiler for Driver {
 /* ---------- Browser helpers ---------- */
 
 #[cfg(not(target_os = "windows"))]
-
 fn file_url_from_path(p: &Path) -> String {
     let abs = std::fs::canonicalize(p).unwrap_or_else(|e| {
         eprintln!("Failed to canonicalize '{}': {}", p.display(), e);
@@ -91,14 +87,31 @@ fn open_in_chrome(html_path: &Path) {
 
 /* ---------- Main ---------- */
 
+fn usage() -> ! {
+    eprintln!("Usage: lolcompiler [--error-limit N] [--include-root DIR] <input.lol>");
+    std::process::exit(1);
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: lolcompiler <input.lol>");
-        std::process::exit(1);
+    let mut args = env::args().skip(1);
+    let mut error_limit = None;
+    let mut include_root = None;
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        if arg == "--error-limit" {
+            let n = args.next().and_then(|n| n.parse::<usize>().ok()).filter(|n| *n > 0);
+            error_limit = Some(n.unwrap_or_else(|| usage()));
+        } else if arg == "--include-root" {
+            include_root = Some(PathBuf::from(args.next().unwrap_or_else(|| usage())));
+        } else if input.is_none() {
+            input = Some(arg);
+        } else {
+            usage();
+        }
     }
+    let Some(input_path) = input else { usage() };
+    let input_path = &input_path;
 
-    let input_path = &args[1];
     if !input_path.to_ascii_lowercase().ends_with(".lol") {
         eprintln!("Error: input must have .lol extension (per project spec).");
         std::process::exit(1);
@@ -110,8 +123,33 @@ fn main() {
     });
 
     // Compile
-    let fe = FrontEnd::new(&source, input_path);
-    let html = fe.run();
+    // Unless told otherwise, includes may read anything next to (or below) the input file.
+    let include_root = include_root.or_else(|| {
+        Path::new(input_path).parent().map(|dir| if dir.as_os_str().is_empty() { PathBuf::from(".") } else { dir.to_path_buf() })
+    });
+    let options = CompileOptions {
+        source_name: input_path.clone(),
+        error_limit,
+        include_root,
+    };
+    let html = compile_with_status(&source, &options).unwrap_or_else(|Failure { errors, aborted }| {
+        // Colorize only when a human is watching stderr.
+        let color = std::io::stderr().is_terminal();
+        for e in &errors {
+            // Errors from `#GIMMEH FILEZ` point into the included file.
+            let text = match &e.file {
+                Some(file) if file != input_path => fs::read_to_string(file).unwrap_or_default(),
+                _ => source.clone(),
+            };
+            eprintln!("{}", e.render(&text, color));
+        }
+        if aborted {
+            eprintln!("Stopped after {} errors (--error-limit).", errors.len());
+        } else if errors.len() > 1 {
+            eprintln!("Compilation failed with {} errors.", errors.len());
+        }
+        std::process::exit(1);
+    });
 
     // Write output .html next to input
     let mut out = PathBuf::from(input_path);
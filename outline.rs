@@ -0,0 +1,211 @@
+//! Second pass over a parsed document: things that need to see the whole
+//! tree, which the single-pass parser cannot know when it emits a node.
+//!
+//! - every heading gets a unique id (its anchor, or a slug of its text with
+//!   `-1`, `-2`, ... on repeats), and author-chosen ids that clash are reported;
+//! - every `#GIMMEH TOC #MKAY` placeholder is filled with the document's headings;
+//! - every `#GIMMEH REFZ` cross-reference gets its target's heading text or
+//!   number, and dangling ones are reported.
+//!
+//! Content from `#GIMMEH FILEZ` is numbered and linked like the rest of the
+//! document; errors in it name the included file.
+
+use std::collections::HashMap;
+
+use crate::ast::{Document, Node, NodeKind, TocEntry};
+use crate::error::CompileError;
+use crate::htmlgen::slugify;
+use crate::token::Span;
+
+/// Resolve heading ids, tables of contents and cross-references in place,
+/// returning the semantic errors found.
+pub fn resolve(doc: &mut Document, source_name: &str) -> Vec<CompileError> {
+    let mut outline = Outline { source_name, ..Default::default() };
+    outline.claim_ids(&mut doc.body);
+    outline.number_blocks(&mut doc.body);
+    outline.fill(&mut doc.body);
+    for note in &mut doc.footnotes {
+        outline.file = note.file.clone();
+        outline.fill(&mut note.children);
+    }
+    outline.errors
+}
+
+/// Where an author-chosen id was first used.
+struct Claim {
+    span: Span,
+    file: Option<String>,
+    anchor: bool,
+}
+
+/// What a cross-reference to an anchor links to.
+struct Target {
+    text: String,
+    span: Span,
+    file: Option<String>,
+}
+
+#[derive(Default)]
+struct Outline<'a> {
+    source_name: &'a str,
+    // The included file being walked; `None` for the main file.
+    file: Option<String>,
+    // How many times each id has been handed out.
+    slugs: HashMap<String, usize>,
+    // Section ids and anchor names, by id.
+    claims: HashMap<String, Claim>,
+    // Headings in document order.
+    entries: Vec<TocEntry>,
+    // Anchor name -> link text, e.g. the heading or "Table 2".
+    targets: HashMap<String, Target>,
+    // Paragraphs, lists and tables seen so far, for their numbers.
+    counts: HashMap<&'static str, usize>,
+    errors: Vec<CompileError>,
+}
+
+impl Outline<'_> {
+    /// Reserve author-chosen ids up front so generated heading slugs avoid them.
+    fn claim_ids(&mut self, nodes: &mut [Node]) {
+        for node in nodes {
+            if let NodeKind::Section { id: Some(id), .. } = &node.kind {
+                self.claim(id, node.span, false);
+            }
+            if let Some(anchor) = &node.anchor {
+                self.claim(anchor, node.span, true);
+            }
+            let outer = self.enter(&node.kind);
+            if let Some(children) = node.kind.children_mut() {
+                self.claim_ids(children);
+            }
+            self.file = outer;
+        }
+    }
+
+    /// Claim `id` for the section or anchored block at `span`, reporting it
+    /// if footnotes own it or another section or anchor took it first.
+    fn claim(&mut self, id: &str, span: Span, anchor: bool) {
+        *self.slugs.entry(id.to_string()).or_insert(0) += 1;
+        let error = |message: String| CompileError::semantic(message).with_span(span).in_file(self.file_name());
+        if is_footnote_id(id) {
+            let err = error(format!("id '{}' is reserved for footnotes", id))
+                .with_label("footnotes use this id")
+                .with_help("footnote ids are `fn-1`, `fnref-1`, ...; pick another name");
+            self.errors.push(err);
+            return;
+        }
+        match self.claims.get(id) {
+            // Two anchors with one name are reported with what they label.
+            Some(first) if first.anchor && anchor => {}
+            Some(first) => {
+                let mut err = error(format!("id '{}' is used more than once", id))
+                    .with_label("second use")
+                    .with_help("give each section and anchored block its own id");
+                if first.file == self.file {
+                    err = err.with_related(first.span, "first used here");
+                }
+                self.errors.push(err);
+            }
+            None => {
+                self.claims.insert(id.to_string(), Claim { span, file: self.file.clone(), anchor });
+            }
+        }
+    }
+
+    /// Assign heading ids and record what every anchor refers to, in document order.
+    fn number_blocks(&mut self, nodes: &mut [Node]) {
+        for node in nodes {
+            let label = match &mut node.kind {
+                NodeKind::Heading { level, text, id } => {
+                    let id = match &node.anchor {
+                        Some(anchor) => id.insert(anchor.clone()).clone(),
+                        None => id.get_or_insert_with(|| unique_slug(&mut self.slugs, text)).clone(),
+                    };
+                    self.entries.push(TocEntry { level: *level, text: text.clone(), id });
+                    Some(text.trim().to_string())
+                }
+                NodeKind::Paragraph(_) => Some(self.next_number("Paragraph")),
+                NodeKind::List(_) | NodeKind::NumberedList { .. } => Some(self.next_number("List")),
+                NodeKind::Table(_) => Some(self.next_number("Table")),
+                _ => None,
+            };
+            if let (Some(anchor), Some(text)) = (&node.anchor, label) {
+                let target = Target { text, span: node.span, file: self.file.clone() };
+                if let Some(first) = self.targets.get(anchor) {
+                    let mut err = CompileError::semantic(format!("anchor '{}' is defined more than once", anchor))
+                        .with_span(node.span)
+                        .in_file(self.file_name())
+                        .with_label("second definition")
+                        .with_help("give each anchored block its own name");
+                    // Related spans are shown against the same source text.
+                    if first.file == self.file {
+                        err = err.with_related(first.span, "first defined here");
+                    }
+                    self.errors.push(err);
+                } else {
+                    self.targets.insert(anchor.clone(), target);
+                }
+            }
+            let outer = self.enter(&node.kind);
+            if let Some(children) = node.kind.children_mut() {
+                self.number_blocks(children);
+            }
+            self.file = outer;
+        }
+    }
+
+    /// Switch to the included file when walking into `#GIMMEH FILEZ` content,
+    /// returning the file to go back to afterwards.
+    fn enter(&mut self, kind: &NodeKind) -> Option<String> {
+        match kind {
+            NodeKind::Include { file, .. } => self.file.replace(file.clone()),
+            _ => self.file.clone(),
+        }
+    }
+
+    fn file_name(&self) -> &str {
+        self.file.as_deref().unwrap_or(self.source_name)
+    }
+
+    fn next_number(&mut self, kind: &'static str) -> String {
+        let n = self.counts.entry(kind).or_insert(0);
+        *n += 1;
+        format!("{} {}", kind, n)
+    }
+
+    /// Fill tables of contents and cross-references.
+    fn fill(&mut self, nodes: &mut [Node]) {
+        for node in nodes {
+            match &mut node.kind {
+                NodeKind::Toc(toc) => *toc = self.entries.clone(),
+                NodeKind::CrossRef { target, text } => match self.targets.get(target.as_str()) {
+                    Some(found) => *text = Some(found.text.clone()),
+                    None => self.errors.push(CompileError::semantic(format!("reference to undefined anchor '{}'", target))
+                        .with_span(node.span)
+                        .in_file(self.file_name())
+                        .with_label("no block has this anchor")
+                        .with_help(format!("put `#GIMMEH ANKR {} #MKAY` right before the block to link to", target))),
+                },
+                kind => {
+                    let outer = self.enter(kind);
+                    if let Some(children) = kind.children_mut() {
+                        self.fill(children);
+                    }
+                    self.file = outer;
+                }
+            }
+        }
+    }
+}
+
+/// The ids htmlgen gives footnotes and their markers: `fn-N` and `fnref-N`.
+fn is_footnote_id(id: &str) -> bool {
+    let number = id.strip_prefix("fnref-").or_else(|| id.strip_prefix("fn-"));
+    number.is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn unique_slug(slugs: &mut HashMap<String, usize>, text: &str) -> String {
+    let base = slugify(text);
+    let seen = slugs.entry(base.clone()).or_insert(0);
+    *seen += 1;
+    if *seen == 1 { base } else { format!("{}-{}", base, *seen - 1) }
+}
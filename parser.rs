@@ -1,198 +1,702 @@
 // src/parser.rs
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{Document, Footnote, Head, Node, NodeKind, NumberStyle, TextStyle, Title};
+use crate::error::{CompileError, Failure};
+use crate::htmlgen;
 use crate::lexer::Lexer;
+use crate::outline;
 use crate::scope::Scope;
-use crate::token::Token;
-use crate::htmlgen::Html;
+use crate::token::{Span, Token};
+
+/// A parse step either succeeds or hands back an error that has not been
+/// recorded yet; the nearest recovery point records it and resynchronizes.
+pub type ParseResult = Result<(), CompileError>;
 
 // Trait signatures from the project handout (Option 1)
 #[allow(dead_code)]
 pub trait SyntaxAnalyzer {
-    fn parse_lolcode(&mut self);
-    fn parse_head(&mut self);
-    fn parse_title(&mut self);
-    fn parse_comment(&mut self);
-    fn parse_body(&mut self);
-    fn parse_paragraph(&mut self);
-    fn parse_inner_paragraph(&mut self);
-    fn parse_inner_text(&mut self);
-    fn parse_variable_define(&mut self);
-    fn parse_variable_use(&mut self);
-    fn parse_bold(&mut self);
-    fn parse_italics(&mut self);
-    fn parse_list(&mut self);
-    fn parse_list_items(&mut self);
-    fn parse_inner_list(&mut self);
-    fn parse_audio(&mut self);
-    fn parse_video(&mut self);
-    fn parse_newline(&mut self);
-    fn parse_text(&mut self);
+    fn parse_lolcode(&mut self) -> ParseResult;
+    fn parse_head(&mut self) -> ParseResult;
+    fn parse_title(&mut self) -> ParseResult;
+    fn parse_comment(&mut self) -> ParseResult;
+    fn parse_body(&mut self) -> ParseResult;
+    fn parse_paragraph(&mut self) -> ParseResult;
+    fn parse_quote(&mut self) -> ParseResult;
+    fn parse_section(&mut self) -> ParseResult;
+    fn parse_rule(&mut self) -> ParseResult;
+    fn parse_toc(&mut self) -> ParseResult;
+    fn parse_anchor(&mut self) -> ParseResult;
+    fn parse_cross_ref(&mut self) -> ParseResult;
+    fn parse_include(&mut self) -> ParseResult;
+    fn parse_cite(&mut self) -> ParseResult;
+    fn parse_inner_paragraph(&mut self) -> ParseResult;
+    fn parse_inner_text(&mut self) -> ParseResult;
+    fn parse_variable_define(&mut self) -> ParseResult;
+    fn parse_variable_use(&mut self) -> ParseResult;
+    fn parse_bold(&mut self) -> ParseResult;
+    fn parse_italics(&mut self) -> ParseResult;
+    fn parse_style(&mut self) -> ParseResult;
+    fn parse_inline_code(&mut self) -> ParseResult;
+    fn parse_footnote(&mut self) -> ParseResult;
+    fn parse_list(&mut self) -> ParseResult;
+    fn parse_numbered_list(&mut self) -> ParseResult;
+    fn parse_list_items(&mut self) -> ParseResult;
+    fn parse_inner_list(&mut self) -> ParseResult;
+    fn parse_audio(&mut self) -> ParseResult;
+    fn parse_video(&mut self) -> ParseResult;
+    fn parse_raw(&mut self) -> ParseResult;
+    fn parse_heading(&mut self) -> ParseResult;
+    fn parse_link(&mut self) -> ParseResult;
+    fn parse_image(&mut self) -> ParseResult;
+    fn parse_table(&mut self) -> ParseResult;
+    fn parse_code_block(&mut self) -> ParseResult;
+    fn parse_table_row(&mut self) -> ParseResult;
+    fn parse_table_cell(&mut self) -> ParseResult;
+    fn parse_newline(&mut self) -> ParseResult;
+    fn parse_text(&mut self) -> ParseResult;
 }
 
 pub struct Parser<'a> {
     lexer: Lexer,
     look: Token,
-    html: Html,
+    span: Span, // where `look` came from
+    prev_end: usize, // end offset of the token before `look`
+    tag_start: Span, // the `#MAEK`/`#GIMMEH` that introduced the current construct
+    doc: Document,
+    frames: Vec<Vec<Node>>, // children being collected for each open construct
     scope: Scope,
-    _source_name: &'a str,
+    source_name: &'a str,
+    in_link: bool, // <a> cannot nest
+    in_cell: bool, // table cells hold inline content only
+    allow_blocks: bool, // lists may appear in the inline content being parsed (ITEM bodies)
+    in_quote: bool, // #GIMMEH CITE is allowed
+    in_footnote: bool, // footnotes cannot nest
+    section_depth: u8, // open #MAEK SECSHUN blocks, for their heading levels
+    includes: Vec<(PathBuf, String)>, // this file and the files including it, outermost first
+    include_root: Option<PathBuf>, // directory `#GIMMEH FILEZ` may read from; `None` turns includes off
+    errors: Vec<CompileError>, // everything reported so far, in source order
+    error_limit: Option<usize>,
+    aborted: bool, // set once an error past `error_limit` is found; parsing then unwinds
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &str, source_name: &'a str) -> Self {
-        let mut lex = Lexer::new(input);
-        let look = lex.next_token();
-        Self {
-            lexer: lex,
-            look,
-            html: Html::default(),
+        let mut p = Self {
+            lexer: Lexer::new(input),
+            look: Token::Eof,
+            span: Span::default(),
+            prev_end: 0,
+            tag_start: Span::default(),
+            doc: Document::default(),
+            frames: vec![Vec::new()],
             scope: Scope::new(),
-            _source_name: source_name,
+            source_name,
+            in_link: false,
+            in_cell: false,
+            allow_blocks: false,
+            in_quote: false,
+            in_footnote: false,
+            section_depth: 0,
+            includes: fs::canonicalize(source_name).map(|p| vec![(p, source_name.to_string())]).unwrap_or_default(),
+            include_root: None,
+            errors: Vec::new(),
+            error_limit: None,
+            aborted: false,
+        };
+        // Lexical errors are recorded by bump(); nothing to propagate yet.
+        let _ = p.bump();
+        p
+    }
+
+    /// Stop parsing once this many errors have been reported (`None` = no limit).
+    pub fn with_error_limit(mut self, limit: Option<usize>) -> Self {
+        self.error_limit = limit;
+        self
+    }
+
+    /// Let `#GIMMEH FILEZ` read files under `root` (`None` = includes are an error).
+    pub fn with_include_root(mut self, root: Option<PathBuf>) -> Self {
+        self.include_root = root;
+        self
+    }
+
+    /// Advance to the next token. Lexical errors are recorded and the bad
+    /// lexeme skipped, so the parser only ever sees well-formed tokens.
+    fn bump(&mut self) -> ParseResult {
+        loop {
+            match self.lexer.next_token() {
+                Ok(next) => {
+                    self.prev_end = self.span.end;
+                    self.look = next.token;
+                    self.span = next.span;
+                    return Ok(());
+                }
+                Err(e) => self.report(e.in_file(self.source_name))?,
+            }
+        }
+    }
+
+    /// Record an error. Once `error_limit` errors are recorded, the next one
+    /// is dropped instead and the parse unwinds, so `aborted` means errors
+    /// went unreported.
+    fn report(&mut self, err: CompileError) -> ParseResult {
+        if self.aborted || self.error_limit.is_some_and(|limit| self.errors.len() >= limit) {
+            self.aborted = true;
+            return Err(err);
+        }
+        self.errors.push(err);
+        Ok(())
+    }
+
+    /// Panic-mode recovery: record `err`, skip to the next `#OIC`, `#MKAY`,
+    /// `#MAEK` or `#KTHXBYE`, and consume it if it is one of `consume`.
+    fn recover(&mut self, err: CompileError, consume: &[Token]) -> ParseResult {
+        self.report(err)?;
+        while !matches!(
+            self.look,
+            Token::HashOic | Token::HashMKay | Token::HashMaek | Token::HashKthxbye | Token::Eof
+        ) {
+            self.bump()?;
+        }
+        let here = std::mem::discriminant(&self.look);
+        if consume.iter().any(|t| std::mem::discriminant(t) == here) {
+            self.bump()?;
+        }
+        Ok(())
+    }
+
+    /// Syntax error located at the current lookahead.
+    fn syntax_error<S: Into<String>>(&self, message: S) -> CompileError {
+        CompileError::syntax(message).with_span(self.span).in_file(self.source_name)
+    }
+
+    /// The block opened by `open` at `opener` was not closed by `close`.
+    fn unclosed(&self, opener: Span, open: &str, close: &str) -> CompileError {
+        let message = match self.look {
+            Token::Eof => format!("unexpected end of input inside `{}`", open),
+            _ => format!("expected `{}`, found {}", close, self.look),
+        };
+        self.syntax_error(message)
+            .with_label(format!("expected `{}`", close))
+            .with_related(opener, format!("`{}` opened here", open))
+            .with_help(format!("expected `{}` to close this `{}`", close, open))
+    }
+
+    /// Eat a `#MAEK`/`#GIMMEH`, remembering where the construct it introduces starts.
+    fn eat_tag(&mut self, expected: Token) -> ParseResult {
+        self.tag_start = self.span;
+        self.eat(expected)
+    }
+
+    /// Is `look` the `#MAEK` of a `LIST` or `NUMBRD LIST`?
+    fn at_list(&mut self) -> bool {
+        matches!(self.look, Token::HashMaek)
+            && matches!(self.lexer.peek_token(), Ok(t) if matches!(t.token, Token::List | Token::Numbrd))
+    }
+
+    /// Span from `start` through the last token consumed.
+    fn span_since(&self, start: Span) -> Span {
+        Span { end: self.prev_end.max(start.start), ..start }
+    }
+
+    /// Start collecting children for a composite node.
+    fn open_frame(&mut self) {
+        self.frames.push(Vec::new());
+    }
+
+    fn close_frame(&mut self) -> Vec<Node> {
+        self.frames.pop().unwrap_or_default()
+    }
+
+    /// Append a finished node (spanning `start` up to here) to the innermost construct.
+    fn emit(&mut self, kind: NodeKind, start: Span) {
+        let node = Node { kind, span: self.span_since(start), anchor: None };
+        if let Some(frame) = self.frames.last_mut() {
+            frame.push(node);
         }
     }
 
-    fn eat(&mut self, expected: Token) {
+    fn eat(&mut self, expected: Token) -> ParseResult {
         if std::mem::discriminant(&self.look) == std::mem::discriminant(&expected) {
-            self.look = self.lexer.next_token();
+            self.bump()
         } else {
-            eprintln!("Syntax error: expected {:?}, found {:?}", expected, self.look);
-            std::process::exit(1);
+            Err(self.syntax_error(format!("expected {}, found {}", expected, self.look))
+                .with_label(format!("expected {}", expected)))
         }
     }
 
     // (Now unused, keep for spec parity; silence warning if you want to keep it)
     #[allow(dead_code)]
-    fn eat_any_text(&mut self) -> String {
+    fn eat_any_text(&mut self) -> Result<String, CompileError> {
         if let Token::Text(t) = self.look.clone() {
-            self.look = self.lexer.next_token();
-            t
+            self.bump()?;
+            Ok(t)
         } else {
-            eprintln!("Syntax error: expected TEXT, found {:?}", self.look);
-            std::process::exit(1);
+            Err(self.syntax_error(format!("expected text, found {}", self.look)))
         }
     }
 
     /// Helper: collect TEXT tokens until we hit #MKAY, then consume #MKAY.
-    fn read_text_until_mkay(&mut self) -> String {
+    fn read_text_until_mkay(&mut self) -> Result<String, CompileError> {
         let mut out = String::new();
         while !matches!(self.look, Token::HashMKay | Token::Eof) {
             match self.look.clone() {
                 Token::Text(t) => {
                     if !out.is_empty() { out.push(' '); }
                     out.push_str(&t);
-                    self.look = self.lexer.next_token();
+                    self.bump()?;
                 }
                 _ => {
-                    eprintln!("Syntax error: only text is allowed before #MKAY here");
-                    std::process::exit(1);
+                    return Err(self.syntax_error("only text is allowed before #MKAY here"));
+                }
+            }
+        }
+        self.eat(Token::HashMKay)?;
+        Ok(out)
+    }
+
+    /// Helper: collect consecutive TEXT tokens as they were written, putting a
+    /// space back only where the source had whitespace between them.
+    fn read_spaced_text(&mut self) -> Result<String, CompileError> {
+        let mut out = String::new();
+        while let Token::Text(t) = &self.look {
+            if !out.is_empty() && self.span.start > self.prev_end {
+                out.push(' ');
+            }
+            out.push_str(t);
+            self.bump()?;
+        }
+        Ok(out)
+    }
+
+    /// Like `read_spaced_text`, but word by word with each word's span, so
+    /// errors can point at a single word. Touching pieces are one word.
+    fn read_words(&mut self) -> Result<Vec<(String, Span)>, CompileError> {
+        let mut words: Vec<(String, Span)> = Vec::new();
+        while let Token::Text(t) = &self.look {
+            let touching = !words.is_empty() && self.span.start == self.prev_end;
+            // Text holding `-`, `?` and the like runs on to the end of the line, spaces included.
+            let mut at = 0;
+            while let Some(skip) = t[at..].find(|c: char| !c.is_whitespace()) {
+                at += skip;
+                let len = t[at..].find(char::is_whitespace).unwrap_or(t.len() - at);
+                let span = Span {
+                    start: self.span.start + at,
+                    end: self.span.start + at + len,
+                    line: self.span.line,
+                    column: self.span.column + t[..at].chars().count(),
+                };
+                match words.last_mut() {
+                    Some((word, last)) if touching && at == 0 => {
+                        word.push_str(&t[..len]);
+                        last.end = span.end;
+                    }
+                    _ => words.push((t[at..at + len].to_string(), span)),
                 }
+                at += len;
             }
+            self.bump()?;
         }
-        self.eat(Token::HashMKay);
-        out
+        Ok(words)
     }
 
     /// Helper: parse “rich” inline content (text, bold, italics, newline, variables, comments)
-    /// until we reach a terminating `#MKAY`. Consumes that `#MKAY`.
-    fn parse_inline_until_mkay(&mut self) {
-        while !matches!(self.look, Token::HashMKay | Token::Eof) {
+    /// until we reach a terminating `#MKAY`. Consumes that `#MKAY`. A block
+    /// boundary first means the construct `name` opened at `opener` was never closed.
+    fn parse_inline_until_mkay(&mut self, opener: Span, name: &str) -> ParseResult {
+        loop {
             match self.look {
-                Token::HashGimmeh => {
-                    self.eat(Token::HashGimmeh);
+                Token::HashMKay => return self.eat(Token::HashMKay),
+                Token::HashOic | Token::HashKthxbye | Token::Eof => return Err(self.unclosed(opener, name, "#MKAY")),
+                // Nested lists (ITEM) and block-in-link errors are handled per item.
+                Token::HashMaek if !self.allow_blocks && !self.in_link && !self.in_cell => {
+                    return Err(self.unclosed(opener, name, "#MKAY"));
+                }
+                _ => {
+                    if let Err(e) = self.parse_inline_item() {
+                        self.recover(e, &[])?;
+                        let nested_list = self.allow_blocks && matches!(self.look, Token::HashMaek);
+                        if !matches!(self.look, Token::HashMKay) && !nested_list {
+                            // Resynced on a block boundary; the error is already recorded.
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Body of a styling construct (bold, italics): rich inline content up
+    /// to `#MKAY`, returned as the styled node's children.
+    fn parse_styled_body(&mut self, opener: Span, name: &str) -> (Vec<Node>, ParseResult) {
+        self.open_frame();
+        let outer = std::mem::replace(&mut self.allow_blocks, false);
+        let result = self.parse_inline_until_mkay(opener, name);
+        self.allow_blocks = outer;
+        (self.close_frame(), result)
+    }
+
+    /// One element of rich inline content.
+    fn parse_inline_item(&mut self) -> ParseResult {
+        match self.look {
+            Token::HashGimmeh => {
+                self.eat_tag(Token::HashGimmeh)?;
+                match self.look {
+                    Token::Bold    => self.parse_bold()?,
+                    Token::Italics => self.parse_italics()?,
+                    Token::Underlin | Token::Strike | Token::Supr | Token::Subz | Token::Hilite => self.parse_style()?,
+                    Token::Kode    => self.parse_inline_code()?,
+                    Token::Futnote => self.parse_footnote()?,
+                    Token::Refz    => self.parse_cross_ref()?,
+                    Token::Newline => self.parse_newline()?,
+                    Token::Htmlz   => self.parse_raw()?,
+                    Token::Linkz   => self.parse_link()?,
+                    Token::Pikchur => self.parse_image()?,
+                    _ => {
+                        return Err(self.syntax_error("unsupported #GIMMEH construct inside this block"));
+                    }
+                }
+            }
+            // Sub-lists inside an ITEM
+            Token::HashMaek if self.allow_blocks => {
+                self.eat_tag(Token::HashMaek)?;
+                match self.look {
+                    Token::List   => self.parse_list()?,
+                    Token::Numbrd => self.parse_numbered_list()?,
+                    _ => {
+                        // Skip the whole foreign block so its #OIC does not close the ITEM
+                        let err = self.syntax_error(format!("only lists can be nested inside an ITEM, found {}", self.look));
+                        self.recover(err, &[Token::HashOic])?;
+                    }
+                }
+            }
+            Token::HashMaek if self.in_cell && !self.in_link => {
+                let maek = self.span;
+                self.bump()?;
+                let err = if matches!(self.look, Token::List | Token::Numbrd) {
+                    CompileError::syntax("lists are not allowed in a table cell")
+                        .with_help("put the list after the table, or give each entry its own cell")
+                } else {
+                    CompileError::syntax("a table cell cannot contain a block")
+                };
+                self.report(err.with_span(maek).in_file(self.source_name).with_label("inside this cell"))?;
+                // Skip the whole block, nested blocks and their #MKAYs included, so the cell carries on after it.
+                let mut open = 1;
+                while open > 0 && !matches!(self.look, Token::HashKthxbye | Token::Eof) {
                     match self.look {
-                        Token::Bold    => self.parse_bold(),
-                        Token::Italics => self.parse_italics(),
-                        Token::Newline => self.parse_newline(),
-                        _ => {
-                            eprintln!("Unsupported #GIMMEH construct inside this block");
-                            std::process::exit(1);
+                        Token::HashMaek => open += 1,
+                        Token::HashOic => open -= 1,
+                        _ => {}
+                    }
+                    self.bump()?;
+                }
+            }
+            Token::HashMaek if self.in_link => {
+                let err = self.syntax_error("link text cannot contain a block");
+                self.bump()?; // step past #MAEK so recovery skips the block itself
+                self.recover(err, &[Token::HashOic])?;
+            }
+            Token::HashLemmeSee => self.parse_variable_use()?,
+            Token::Text(_) => self.parse_text()?,
+            Token::HashObtW => self.parse_comment()?,
+            _ => {
+                return Err(self.syntax_error(format!("unexpected {} inside inline block", self.look)));
+            }
+        }
+        Ok(())
+    }
+
+    /// One entry of a LIST body: an item, a line break or a comment.
+    fn parse_list_entry(&mut self) -> ParseResult {
+        match self.look {
+            Token::HashGimmeh => {
+                self.eat_tag(Token::HashGimmeh)?;
+                match self.look {
+                    Token::Item => self.parse_list_items(),
+                    Token::Newline => self.parse_newline(), // allow line breaks in list body
+                    _ => Err(self.syntax_error("inside LIST: expected ITEM after #GIMMEH")),
+                }
+            }
+            Token::HashObtW => self.parse_comment(),
+            // Be strict: only items/comments/newlines allowed inside a LIST
+            _ => Err(self.syntax_error(format!("unexpected {} inside LIST", self.look))),
+        }
+    }
+
+    /// The parsed tree (meaningful only when no errors were reported).
+    pub fn into_document(self) -> Document { self.doc }
+
+    /// Find and read the file named by `#GIMMEH FILEZ <path>`: `name` is the
+    /// path relative to the including file, and must stay under the include root.
+    fn read_include(&self, path: &str, name: &str, span: Span) -> Result<(PathBuf, String), CompileError> {
+        let error = |message: String| CompileError::semantic(message).with_span(span).in_file(self.source_name);
+        let Some(root) = &self.include_root else {
+            return Err(error("`#GIMMEH FILEZ` is not enabled".to_string())
+                .with_help("set `CompileOptions::include_root` to the directory includes may read from"));
+        };
+        if Path::new(path).is_absolute() {
+            return Err(error(format!("include path '{}' must be relative", path))
+                .with_help("the path is relative to the file containing `#GIMMEH FILEZ`"));
+        }
+        // The kind, not the OS message, so the wording is the same everywhere.
+        let unreadable = |e: std::io::Error| {
+            error(format!("cannot read included file '{}'", name))
+                .with_label(e.kind().to_string())
+                .with_help("the path is relative to the file containing `#GIMMEH FILEZ`")
+        };
+        let canonical = fs::canonicalize(name).map_err(unreadable)?;
+        let root = fs::canonicalize(root)
+            .map_err(|e| error(format!("include root '{}' cannot be read", root.display())).with_label(e.kind().to_string()))?;
+        if !canonical.starts_with(&root) {
+            return Err(error(format!("included file '{}' is outside the include root", name))
+                .with_label("not under the include root")
+                .with_help("only files under `CompileOptions::include_root` can be included"));
+        }
+        let source = fs::read_to_string(&canonical).map_err(unreadable)?;
+        Ok((canonical, source))
+    }
+
+    /// The body content of an included file: body constructs up to the end
+    /// of input, without `#HAI`, a head or `#KTHXBYE`.
+    fn parse_fragment(&mut self) -> ParseResult {
+        while !matches!(self.look, Token::Eof) {
+            if matches!(self.look, Token::HashHai | Token::HashKthxbye) {
+                self.report(self.syntax_error(format!("an included file holds body content only, found {}", self.look))
+                    .with_help("remove `#HAI` and `#KTHXBYE` from the included file"))?;
+                self.bump()?;
+            } else if let Err(e) = self.parse_body() {
+                self.recover(e, &[Token::HashMKay, Token::HashOic])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Everything reported while parsing, in the order it was found.
+    pub fn errors(&self) -> &[CompileError] { &self.errors }
+}
+
+impl<'a> Parser<'a> {
+    fn parse_list_body(&mut self, opener: Span, name: &str) -> ParseResult {
+        // Inside a list, expect zero or more "#GIMMEH ITEM ... #MKAY"
+        loop {
+            match self.look {
+                Token::HashOic => return self.eat(Token::HashOic),
+                Token::Eof | Token::HashMaek | Token::HashKthxbye => {
+                    return Err(self.unclosed(opener, name, "#OIC"));
+                }
+                _ => {
+                    if let Err(e) = self.parse_list_entry() {
+                        self.recover(e, &[Token::HashMKay])?;
+                    }
+                }
+            }
+        }
+    }
+
+    fn parse_table_body(&mut self, opener: Span) -> ParseResult {
+        let mut first_row: Option<(usize, Span)> = None; // cell count and span of the first row
+        let mut seen_body = false;
+        loop {
+            match self.look {
+                Token::HashOic => return self.eat(Token::HashOic),
+                Token::Eof | Token::HashKthxbye => {
+                    return Err(self.unclosed(opener, "#MAEK TABL", "#OIC"));
+                }
+                Token::HashMaek => {
+                    self.eat_tag(Token::HashMaek)?;
+                    if matches!(self.look, Token::Row | Token::Headr) {
+                        match self.parse_table_row() {
+                            Ok(()) => self.check_table_row(&mut first_row, &mut seen_body)?,
+                            Err(e) => self.recover(e, &[Token::HashMKay])?,
                         }
+                    } else {
+                        // Skip the whole foreign block so its #OIC does not close the table
+                        let err = self.syntax_error(format!("inside TABL: expected ROW or HEADR ROW after #MAEK, found {}", self.look));
+                        self.recover(err, &[Token::HashOic])?;
+                    }
+                }
+                Token::HashObtW => {
+                    if let Err(e) = self.parse_comment() {
+                        self.recover(e, &[])?;
                     }
                 }
-                Token::HashLemmeSee => self.parse_variable_use(),
-                Token::Text(_) => self.parse_text(),
-                Token::HashObtW => self.parse_comment(),
-                Token::HashMKay => break, // stop condition
                 _ => {
-                    eprintln!("Unexpected token inside inline block: {:?}", self.look);
-                    std::process::exit(1);
+                    let err = self.syntax_error(format!("unexpected {} inside TABL", self.look));
+                    self.recover(err, &[Token::HashMKay])?;
+                }
+            }
+        }
+    }
+
+    fn parse_row_body(&mut self, opener: Span, name: &str) -> ParseResult {
+        loop {
+            match self.look {
+                Token::HashOic => return self.eat(Token::HashOic),
+                Token::Eof | Token::HashMaek | Token::HashKthxbye => {
+                    return Err(self.unclosed(opener, name, "#OIC"));
+                }
+                _ => {
+                    let entry = match self.look {
+                        Token::HashGimmeh => self.eat_tag(Token::HashGimmeh).and_then(|_| match self.look {
+                            Token::Cell => self.parse_table_cell(),
+                            _ => Err(self.syntax_error("inside ROW: expected CELL after #GIMMEH")),
+                        }),
+                        Token::HashObtW => self.parse_comment(),
+                        _ => Err(self.syntax_error(format!("unexpected {} inside ROW", self.look))),
+                    };
+                    if let Err(e) = entry {
+                        self.recover(e, &[Token::HashMKay])?;
+                    }
                 }
             }
         }
-        self.eat(Token::HashMKay);
     }
 
-    pub fn into_html(self) -> String { self.html.finish() }
+    /// Check the row just parsed: it needs as many cells as the first row,
+    /// and header rows have to come before the body rows. The row was fully
+    /// consumed, so problems are recorded without resynchronizing.
+    fn check_table_row(&mut self, first_row: &mut Option<(usize, Span)>, seen_body: &mut bool) -> ParseResult {
+        let Some(Node { kind: NodeKind::Row { header, cells }, span, .. }) = self.frames.last().and_then(|f| f.last()) else {
+            return Ok(());
+        };
+        let (header, span) = (*header, *span);
+        let count = cells.iter().filter(|c| matches!(c.kind, NodeKind::Cell(_))).count();
+        if header && *seen_body {
+            self.report(CompileError::syntax("header row after body rows")
+                .with_span(span)
+                .in_file(self.source_name)
+                .with_help("move the `#MAEK HEADR ROW` rows to the top of the table"))?;
+        }
+        *seen_body |= !header;
+        match *first_row {
+            None => *first_row = Some((count, span)),
+            Some((expected, first_span)) if count != expected => {
+                self.report(CompileError::semantic(format!(
+                    "row has {} cell{} but the first row has {}",
+                    count,
+                    if count == 1 { "" } else { "s" },
+                    expected
+                ))
+                .with_span(span)
+                .in_file(self.source_name)
+                .with_label(format!("expected {} cells", expected))
+                .with_related(first_span, "first row")
+                .with_help("give every row of a table the same number of cells"))?;
+            }
+            Some(_) => {}
+        }
+        Ok(())
+    }
+
+    fn parse_section_contents(&mut self, opener: Span) -> ParseResult {
+        if matches!(self.look, Token::HashItIz) {
+            let start = self.span;
+            self.eat(Token::HashItIz)?;
+            let text = self.read_text_until_mkay()?;
+            let level = (1 + self.section_depth).min(6);
+            self.emit(NodeKind::Heading { level, text: text.trim().to_string(), id: None }, start);
+        }
+        while !matches!(self.look, Token::HashOic | Token::HashKthxbye | Token::Eof) {
+            if let Err(e) = self.parse_body() {
+                self.recover(e, &[Token::HashMKay, Token::HashOic])?;
+            }
+        }
+        if !matches!(self.look, Token::HashOic) {
+            return Err(self.unclosed(opener, "#MAEK SECSHUN", "#OIC"));
+        }
+        self.eat(Token::HashOic)
+    }
+
+    fn parse_paragraph_contents(&mut self, opener: Span, name: &str) -> ParseResult {
+        // Optional immediate var define (per spec)
+        if matches!(self.look, Token::HashIHaz) {
+            self.parse_variable_define()?;
+        }
+
+        // Inner paragraph content
+        self.parse_inner_paragraph()?;
+
+        if !matches!(self.look, Token::HashOic) {
+            return Err(self.unclosed(opener, name, "#OIC"));
+        }
+        self.eat(Token::HashOic)
+    }
 }
 
 impl<'a> SyntaxAnalyzer for Parser<'a> {
-    fn parse_lolcode(&mut self) {
-    // <!doctype html><html>
-    self.html.begin_html();
+    fn parse_lolcode(&mut self) -> ParseResult {
+    let start = self.span;
 
     // Must start with #HAI
     match self.look {
-        Token::HashHai => self.eat(Token::HashHai),
-        _ => {
-            eprintln!("Program must start with #HAI");
-            std::process::exit(1);
-        }
+        Token::HashHai => self.eat(Token::HashHai)?,
+        _ => self.report(self.syntax_error("program must start with #HAI"))?,
     }
 
     // zero or more comments
     while matches!(self.look, Token::HashObtW) {
-        self.parse_comment();
+        self.parse_comment()?;
     }
+    self.doc.prelude = self.frames.last_mut().map(std::mem::take).unwrap_or_default();
 
     // optional head: #MAEK HEAD ... #OIC
     if matches!(self.look, Token::HashMaek) {
         let _save = self.look.clone();
-        self.eat(Token::HashMaek);
-        if matches!(self.look, Token::Head) {
-            self.parse_head();
+        self.eat_tag(Token::HashMaek)?;
+        let head = if matches!(self.look, Token::Head) {
+            self.parse_head()
         } else {
-            eprintln!("Syntax error: after #MAEK expected HEAD or PARAGRAF");
-            std::process::exit(1);
+            Err(self.syntax_error("after #MAEK expected HEAD or PARAGRAF"))
+        };
+        if let Err(e) = head {
+            self.recover(e, &[Token::HashMKay, Token::HashOic])?;
         }
     }
 
-    // Ensure <body> is open so plain text lands inside it
-    self.html.begin_body();
-
     // body: zero or more constructs until #KTHXBYE
     while !matches!(self.look, Token::HashKthxbye | Token::Eof) {
-        self.parse_body();
+        if let Err(e) = self.parse_body() {
+            self.recover(e, &[Token::HashMKay, Token::HashOic])?;
+        }
     }
 
     // Must end with #KTHXBYE
-    self.eat(Token::HashKthxbye);
+    self.eat(Token::HashKthxbye)?;
 
-    // Close </body> and then </html>
-    self.html.end_body();
-
-    // NOTE:
-    // If your htmlgen.finish() already appends </html>, you can remove the next line.
-    // Keep it ONLY if your Html.finish() does NOT auto-close.
-    self.html.end_html();
+    self.doc.body = self.frames.last_mut().map(std::mem::take).unwrap_or_default();
+    self.doc.span = self.span_since(start);
+    Ok(())
 }
 
 
-    fn parse_head(&mut self) {
+    fn parse_head(&mut self) -> ParseResult {
         // we’ve already consumed #MAEK then saw HEAD
-        self.eat(Token::Head);
-        self.html.begin_head();
-        self.parse_title();
-        self.eat(Token::HashOic);
-        self.html.end_head();
+        let start = self.tag_start;
+        self.eat(Token::Head)?;
+        self.parse_title()?;
+        self.eat(Token::HashOic)?;
+        let span = self.span_since(start);
+        if let Some(head) = &mut self.doc.head {
+            head.span = span;
+        }
+        Ok(())
     }
 
-    fn parse_title(&mut self) {
-        self.eat(Token::HashGimmeh);
-        self.eat(Token::Title);
-        let t = self.read_text_until_mkay(); // collect "The Simpsons" etc.
-        self.html.title(&t);
+    fn parse_title(&mut self) -> ParseResult {
+        let start = self.span;
+        self.eat_tag(Token::HashGimmeh)?;
+        self.eat(Token::Title)?;
+        let t = self.read_text_until_mkay()?; // collect "The Simpsons" etc.
+        let title = Title { text: t, span: self.span_since(start) };
+        self.doc.head = Some(Head { title, span: start }); // parse_head widens the span
+        Ok(())
     }
 
-    fn parse_comment(&mut self) {
-        self.eat(Token::HashObtW);
+    fn parse_comment(&mut self) -> ParseResult {
+        let opener = self.span;
+        self.eat(Token::HashObtW)?;
         // accumulate everything until #TLDR
         let mut text = String::new();
         while !matches!(self.look, Token::HashTldr | Token::Eof) {
@@ -200,38 +704,59 @@ impl<'a> SyntaxAnalyzer for Parser<'a> {
                 Token::Text(t) => {
                     if !text.is_empty() { text.push(' '); }
                     text.push_str(&t);
-                    self.look = self.lexer.next_token();
+                    self.bump()?;
                 }
                 _ => {
-                    eprintln!("Syntax error: only text is allowed inside #OBTW ... #TLDR comments");
-                    std::process::exit(1);
+                    return Err(self.syntax_error("only text is allowed inside #OBTW ... #TLDR comments")
+                        .with_related(opener, "comment opened here")
+                        .with_help("expected `#TLDR` to close this `#OBTW`"));
                 }
             }
         }
-        self.eat(Token::HashTldr);
-        self.html.comment(&text);
+        if matches!(self.look, Token::Eof) {
+            return Err(self.unclosed(opener, "#OBTW", "#TLDR"));
+        }
+        self.eat(Token::HashTldr)?;
+        self.emit(NodeKind::Comment(text), opener);
+        Ok(())
     }
 
 
-    fn parse_body(&mut self) {
+    fn parse_body(&mut self) -> ParseResult {
         match self.look {
             Token::HashMaek => {
-                self.eat(Token::HashMaek);
+                self.eat_tag(Token::HashMaek)?;
                 match self.look {
                     Token::Paragraf => self.parse_paragraph(),
                     Token::List     => self.parse_list(),   // NEW: lists
-                    _ => { eprintln!("After #MAEK expected PARAGRAF or LIST"); std::process::exit(1); }
+                    Token::Numbrd   => self.parse_numbered_list(),
+                    Token::Tabl     => self.parse_table(),
+                    Token::Kode     => self.parse_code_block(),
+                    Token::Quotez   => self.parse_quote(),
+                    Token::Secshun  => self.parse_section(),
+                    _ => Err(self.syntax_error("after #MAEK expected PARAGRAF, LIST, NUMBRD LIST, TABL, KODE, QUOTEZ or SECSHUN")),
                 }
             }
             Token::HashGimmeh => {
-                self.eat(Token::HashGimmeh);
+                self.eat_tag(Token::HashGimmeh)?;
                 match self.look {
                     Token::Newline => self.parse_newline(),
                     Token::Bold    => self.parse_bold(),
                     Token::Italics => self.parse_italics(),
+                    Token::Underlin | Token::Strike | Token::Supr | Token::Subz | Token::Hilite => self.parse_style(),
+                    Token::Kode    => self.parse_inline_code(),
                     Token::Soundz  => self.parse_audio(),   // NEW: sound
                     Token::Vidz    => self.parse_video(),   // NEW: video
-                    _ => { eprintln!("Unsupported/Unexpected #GIMMEH construct in body"); std::process::exit(1); }
+                    Token::Htmlz   => self.parse_raw(),
+                    Token::Headr   => self.parse_heading(),
+                    Token::Linkz   => self.parse_link(),
+                    Token::Pikchur => self.parse_image(),
+                    Token::Lyne    => self.parse_rule(),
+                    Token::Toc     => self.parse_toc(),
+                    Token::Ankr    => self.parse_anchor(),
+                    Token::Refz    => self.parse_cross_ref(),
+                    Token::Filez   => self.parse_include(),
+                    _ => Err(self.syntax_error("unsupported/unexpected #GIMMEH construct in body")),
                 }
             }
             Token::HashIHaz => self.parse_variable_define(),
@@ -240,209 +765,748 @@ impl<'a> SyntaxAnalyzer for Parser<'a> {
             Token::HashObtW => self.parse_comment(),
 
             // tolerate stray #MKAY at top level
-            Token::HashMKay => { self.eat(Token::HashMKay); }
+            Token::HashMKay => self.eat(Token::HashMKay),
 
-            _ => { eprintln!("Unexpected token in body: {:?}", self.look); std::process::exit(1); }
+            _ => Err(self.syntax_error(format!("unexpected {} in body", self.look))),
         }
     }
 
-    fn parse_paragraph(&mut self) {
-        self.eat(Token::Paragraf);
+    fn parse_paragraph(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::Paragraf)?;
         self.scope.push(); // new block scope
-        self.html.begin_p();
+        self.open_frame();
 
-        // Optional immediate var define (per spec)
-        if matches!(self.look, Token::HashIHaz) {
-            self.parse_variable_define();
+        // Pop the scope even when the paragraph is malformed.
+        let result = self.parse_paragraph_contents(opener, "#MAEK PARAGRAF");
+
+        let children = self.close_frame();
+        self.emit(NodeKind::Paragraph(children), start);
+        self.scope.pop();
+        result
+    }
+
+    /// `#MAEK QUOTEZ ... #OIC`: paragraph content in its own block scope,
+    /// with at most one `#GIMMEH CITE` attribution.
+    fn parse_quote(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::Quotez)?;
+        self.scope.push();
+        self.open_frame();
+
+        let outer = std::mem::replace(&mut self.in_quote, true);
+        let result = self.parse_paragraph_contents(opener, "#MAEK QUOTEZ");
+        self.in_quote = outer;
+
+        let children = self.close_frame();
+        let extra: Vec<Span> = children
+            .iter()
+            .filter(|c| matches!(c.kind, NodeKind::Cite(_)))
+            .skip(1)
+            .map(|c| c.span)
+            .collect();
+        self.emit(NodeKind::Quote(children), start);
+        self.scope.pop();
+
+        // The quote was consumed, so record without resynchronizing.
+        for span in extra {
+            self.report(CompileError::syntax("a quote can have only one attribution")
+                .with_span(span)
+                .in_file(self.source_name)
+                .with_help("merge the `#GIMMEH CITE` lines into one"))?;
         }
+        result
+    }
 
-        // Inner paragraph content
-        self.parse_inner_paragraph();
+    /// `#MAEK SECSHUN [id] [#IT IZ heading #MKAY]`, then body constructs up
+    /// to `#OIC`, in their own block scope. The heading gets the level below
+    /// the enclosing section's (`<h2>` at the top).
+    fn parse_section(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::Secshun)?;
+
+        // The id is whatever follows on the opening line; touching pieces
+        // (`getting` `-started`) are one word.
+        let id_span = self.span;
+        let mut line = String::new();
+        while let Token::Text(t) = &self.look {
+            if self.span.line != opener.line {
+                break;
+            }
+            if !line.is_empty() && self.span.start > self.prev_end {
+                line.push(' ');
+            }
+            line.push_str(t);
+            self.bump()?;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let id = words.first().map(|w| w.to_string());
+        if words.len() > 1 {
+            self.report(CompileError::syntax(format!("section id must be one word, found `{}`", words.join(" ")))
+                .with_span(id_span)
+                .in_file(self.source_name)
+                .with_help("put the section heading after `#IT IZ`"))?;
+        }
 
-        self.eat(Token::HashOic);
-        self.html.end_p();
+        self.scope.push();
+        self.open_frame();
+        self.section_depth += 1;
+        let result = self.parse_section_contents(opener);
+        self.section_depth -= 1;
+        let children = self.close_frame();
+        self.emit(NodeKind::Section { id, children }, start);
         self.scope.pop();
+        result
     }
 
-    fn parse_inner_paragraph(&mut self) {
-        // zero or more inner-text elements until #OIC
-        while !matches!(self.look, Token::HashOic | Token::Eof) {
-            self.parse_inner_text();
+    fn parse_rule(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Lyne)?;
+        self.eat(Token::HashMKay)?;
+        self.emit(NodeKind::Rule, start);
+        Ok(())
+    }
+
+    /// `#GIMMEH TOC #MKAY`; filled in after parsing, once every heading is known.
+    fn parse_toc(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Toc)?;
+        self.eat(Token::HashMKay)?;
+        self.emit(NodeKind::Toc(Vec::new()), start);
+        Ok(())
+    }
+
+    /// `#GIMMEH ANKR <id> #MKAY` followed by the paragraph, list, heading or
+    /// table it names.
+    fn parse_anchor(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Ankr)?;
+        let name_span = self.span;
+        let name = self.read_spaced_text()?;
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(CompileError::syntax(format!("expected one word as the anchor name, found `{}`", name))
+                .with_span(name_span)
+                .in_file(self.source_name)
+                .with_help("write it as `#GIMMEH ANKR <name> #MKAY`"));
+        }
+        self.eat(Token::HashMKay)?;
+        let anchor_span = self.span_since(start);
+
+        // The anchored block follows directly.
+        let before = self.frames.last().map_or(0, Vec::len);
+        let result = match self.look {
+            Token::HashMaek | Token::HashGimmeh => self.parse_body(),
+            _ => Err(self.syntax_error(format!("expected a block after the anchor, found {}", self.look))),
+        };
+        let block = self.frames.last_mut().filter(|f| f.len() > before).and_then(|f| f.last_mut());
+        match block {
+            Some(node) if matches!(
+                node.kind,
+                NodeKind::Paragraph(_) | NodeKind::List(_) | NodeKind::NumberedList { .. } | NodeKind::Heading { .. } | NodeKind::Table(_)
+            ) => node.anchor = Some(name),
+            Some(_) => self.report(CompileError::syntax("only a paragraph, list, heading or table can have an anchor")
+                .with_span(anchor_span)
+                .in_file(self.source_name)
+                .with_label("anchor for the construct after it"))?,
+            None => {}
+        }
+        result
+    }
+
+    /// `#GIMMEH REFZ <anchor> #MKAY`; the link text is filled in after
+    /// parsing, since the target may come later in the document.
+    fn parse_cross_ref(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Refz)?;
+        if self.in_link {
+            return Err(CompileError::syntax("a cross-reference cannot appear inside link text")
+                .with_span(start)
+                .in_file(self.source_name));
+        }
+        let name_span = self.span;
+        let target = self.read_spaced_text()?;
+        if target.is_empty() || target.contains(char::is_whitespace) {
+            return Err(CompileError::syntax(format!("expected one anchor name, found `{}`", target))
+                .with_span(name_span)
+                .in_file(self.source_name)
+                .with_help("write it as `#GIMMEH REFZ <name> #MKAY`"));
+        }
+        self.eat(Token::HashMKay)?;
+        self.emit(NodeKind::CrossRef { target, text: None }, start);
+        Ok(())
+    }
+
+    /// `#GIMMEH FILEZ <path> #MKAY`: the body content of another file, with
+    /// the path taken relative to the including file and confined to the
+    /// include root; without a root, includes are an error. The included file
+    /// shares the scope at the include, as if its text were pasted there: it
+    /// sees the variables defined so far, and its top-level definitions stay
+    /// visible after it.
+    fn parse_include(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Filez)?;
+        let path_start = self.span;
+        let path = self.read_spaced_text()?;
+        let path_span = self.span_since(path_start);
+        if path.is_empty() {
+            return Err(self.syntax_error("expected a file path after #GIMMEH FILEZ")
+                .with_help("write it as `#GIMMEH FILEZ <path> #MKAY`"));
+        }
+        self.eat(Token::HashMKay)?;
+
+        let name = match self.source_name.rfind(['/', '\\']) {
+            Some(dir) => format!("{}/{}", &self.source_name[..dir], path),
+            None => path.clone(),
+        };
+        let (canonical, source) = match self.read_include(&path, &name, path_span) {
+            Ok(found) => found,
+            Err(e) => return self.report(e),
+        };
+        if let Some(first) = self.includes.iter().position(|(p, _)| *p == canonical) {
+            let chain: Vec<&str> = self.includes[first..].iter().map(|(_, n)| n.as_str()).collect();
+            return self.report(CompileError::semantic(format!("include cycle: {} -> {}", chain.join(" -> "), name))
+                .with_span(path_span)
+                .in_file(self.source_name)
+                .with_label("includes a file that is already being included")
+                .with_help("remove one of the `#GIMMEH FILEZ` lines in the cycle"));
         }
+
+        let mut sub = Parser::new(&source, &name)
+            .with_error_limit(self.error_limit.map(|limit| limit.saturating_sub(self.errors.len())))
+            .with_include_root(self.include_root.clone());
+        sub.includes = self.includes.clone();
+        sub.includes.push((canonical, name.clone()));
+        sub.section_depth = self.section_depth;
+        sub.scope = std::mem::take(&mut self.scope);
+        sub.doc.footnotes = std::mem::take(&mut self.doc.footnotes);
+        let first_note = sub.doc.footnotes.len();
+
+        // Errors that unwound to the top are recorded like any other.
+        let dropped = sub.parse_fragment().err().and_then(|e| sub.report(e).err());
+
+        self.scope = std::mem::take(&mut sub.scope);
+        self.doc.footnotes = std::mem::take(&mut sub.doc.footnotes);
+        for note in &mut self.doc.footnotes[first_note..] {
+            note.file.get_or_insert_with(|| name.clone());
+        }
+        let children = sub.close_frame();
+        let errors = std::mem::take(&mut sub.errors);
+        self.emit(NodeKind::Include { file: name.clone(), children }, start);
+        // Already tagged with the included file's name.
+        for err in errors {
+            self.report(err)?;
+        }
+        if let Some(err) = dropped {
+            // The included file hit the error limit.
+            self.aborted = true;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn parse_cite(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        if !self.in_quote {
+            return Err(self.syntax_error("`#GIMMEH CITE` is only allowed inside `#MAEK QUOTEZ`"));
+        }
+        let opener = self.span;
+        self.eat(Token::Cite)?;
+        let (children, result) = self.parse_styled_body(opener, "#GIMMEH CITE");
+        self.emit(NodeKind::Cite(children), start);
+        result
     }
 
-    fn parse_inner_text(&mut self) {
+    fn parse_inner_paragraph(&mut self) -> ParseResult {
+        // zero or more inner-text elements until #OIC (a block boundary
+        // other than a list instead means the paragraph was never closed)
+        while !matches!(self.look, Token::HashOic | Token::HashMaek | Token::HashKthxbye | Token::Eof)
+            || self.at_list()
+        {
+            if let Err(e) = self.parse_inner_text() {
+                self.recover(e, &[Token::HashMKay])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_inner_text(&mut self) -> ParseResult {
         match self.look {
             Token::HashGimmeh => {
-                self.eat(Token::HashGimmeh);
+                self.eat_tag(Token::HashGimmeh)?;
                 match self.look {
                     Token::Bold    => self.parse_bold(),
                     Token::Italics => self.parse_italics(),
+                    Token::Underlin | Token::Strike | Token::Supr | Token::Subz | Token::Hilite => self.parse_style(),
+                    Token::Kode    => self.parse_inline_code(),
+                    Token::Cite    => self.parse_cite(),
+                    Token::Futnote => self.parse_footnote(),
+                    Token::Refz    => self.parse_cross_ref(),
                     Token::Newline => self.parse_newline(),
-                    _ => { eprintln!("Unsupported #GIMMEH in paragraph"); std::process::exit(1); }
+                    Token::Htmlz   => self.parse_raw(),
+                    Token::Linkz   => self.parse_link(),
+                    Token::Pikchur => self.parse_image(),
+                    _ => Err(self.syntax_error("unsupported #GIMMEH in paragraph")),
+                }
+            }
+            Token::HashMaek => {
+                self.eat_tag(Token::HashMaek)?;
+                match self.look {
+                    Token::List   => self.parse_list(),
+                    Token::Numbrd => self.parse_numbered_list(),
+                    _ => Err(self.syntax_error(format!("only lists can appear inside a paragraph, found {}", self.look))),
                 }
             }
             Token::HashLemmeSee => self.parse_variable_use(),
             Token::Text(_) => self.parse_text(),
             Token::HashObtW => self.parse_comment(),
             // tolerate a stray #MKAY inside a paragraph (consume and continue)
-            Token::HashMKay => { self.eat(Token::HashMKay); }
-            _ => { eprintln!("Unexpected token in paragraph: {:?}", self.look); std::process::exit(1); }
+            Token::HashMKay => self.eat(Token::HashMKay),
+            _ => Err(self.syntax_error(format!("unexpected {} in paragraph", self.look))),
         }
     }
 
-    fn parse_variable_define(&mut self) {
-        self.eat(Token::HashIHaz);
+    fn parse_variable_define(&mut self) -> ParseResult {
+        let start = self.span;
+        self.eat(Token::HashIHaz)?;
         let name = if let Token::Text(t) = self.look.clone() {
-            self.eat(Token::Text(t.clone()));
+            self.eat(Token::Text(t.clone()))?;
             t
         } else {
-            eprintln!("Expected variable name after #I HAZ");
-            std::process::exit(1);
+            return Err(self.syntax_error("expected variable name after #I HAZ"));
         };
-        self.eat(Token::HashItIz);
-        let val = self.read_text_until_mkay();  // ✅ collects entire value before #MKAY
-        self.scope.define(&name, val);
+        self.eat(Token::HashItIz)?;
+        let val = self.read_text_until_mkay()?;  // ✅ collects entire value before #MKAY
+        self.scope.define(&name, val.clone());
+        self.emit(NodeKind::VarDefine { name, value: val }, start);
+        Ok(())
     }
 
 
-    fn parse_variable_use(&mut self) {
+    fn parse_variable_use(&mut self) -> ParseResult {
         // #LEMME SEE <name> #MKAY
-        self.eat(Token::HashLemmeSee);
+        let start = self.span;
+        self.eat(Token::HashLemmeSee)?;
+        let name_span = self.span;
         let name = if let Token::Text(t) = self.look.clone() {
-            self.eat(Token::Text(t.clone()));
+            self.eat(Token::Text(t.clone()))?;
             t
         } else {
-            eprintln!("Expected variable name after #LEMME SEE");
-            std::process::exit(1);
+            return Err(self.syntax_error("expected variable name after #LEMME SEE"));
         };
-        self.eat(Token::HashMKay);
+        self.eat(Token::HashMKay)?;
         if let Some(v) = self.scope.resolve(&name) {
-            self.html.push(&v);
+            self.emit(NodeKind::VarUse { name, value: v }, start);
+            Ok(())
         } else {
-            eprintln!("Static semantic error: variable '{}' used before definition", name);
-            std::process::exit(1);
+            // Nothing to resynchronize after a semantic error; just record it.
+            self.report(CompileError::semantic(format!("variable '{}' used before definition", name))
+                .with_span(name_span)
+                .in_file(self.source_name)
+                .with_label("not defined in this scope")
+                .with_help(format!("define it first with `#I HAZ {} #IT IZ ... #MKAY`", name)))
         }
     }
 
-    fn parse_bold(&mut self) {
-        self.eat(Token::Bold);
-        let t = self.read_text_until_mkay(); // collect multi-word bold text
-        self.html.bold(&t);
+    fn parse_bold(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::Bold)?;
+        let (children, result) = self.parse_styled_body(opener, "#GIMMEH BOLD");
+        self.emit(NodeKind::Bold(children), start);
+        result
     }
 
-    fn parse_italics(&mut self) {
-        self.eat(Token::Italics);
-        let t = self.read_text_until_mkay(); // collect multi-word italics text
-        self.html.italics(&t);
+    fn parse_italics(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::Italics)?;
+        let (children, result) = self.parse_styled_body(opener, "#GIMMEH ITALICS");
+        self.emit(NodeKind::Italics(children), start);
+        result
+    }
+
+    /// `#GIMMEH UNDERLIN|STRIKE|SUPR|SUBZ|HILITE ... #MKAY`
+    fn parse_style(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let style = match self.look {
+            Token::Underlin => TextStyle::Underline,
+            Token::Strike => TextStyle::Strike,
+            Token::Supr => TextStyle::Superscript,
+            Token::Subz => TextStyle::Subscript,
+            Token::Hilite => TextStyle::Highlight,
+            _ => return Err(self.syntax_error(format!("expected an inline style, found {}", self.look))),
+        };
+        let (opener, name) = (self.span, format!("#GIMMEH {}", self.look).replace('`', ""));
+        self.bump()?;
+        let (children, result) = self.parse_styled_body(opener, &name);
+        self.emit(NodeKind::Styled { style, children }, start);
+        result
+    }
+
+    /// `#GIMMEH KODE ... #MKAY`. Like a code block, the content is read
+    /// straight from the lexer so words and spacing survive as written.
+    fn parse_inline_code(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        let code = self.lexer.read_verbatim_until("#MKAY");
+        self.span.end = self.lexer.here().start;
+        if code.is_none() {
+            self.report(CompileError::syntax("unexpected end of input inside `#GIMMEH KODE`")
+                .with_span(self.lexer.here())
+                .in_file(self.source_name)
+                .with_related(opener, "`#GIMMEH KODE` opened here")
+                .with_help("close the code with `#MKAY`"))?;
+        }
+        self.bump()?;
+        if let Some(code) = code {
+            self.emit(NodeKind::Code(code.trim().to_string()), start);
+        }
+        Ok(())
+    }
+
+    /// `#GIMMEH FUTNOTE ... #MKAY`: the content moves to the document's
+    /// footnotes and a numbered reference takes its place.
+    fn parse_footnote(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::Futnote)?;
+        if self.in_link || self.in_footnote {
+            let inside = if self.in_link { "link text" } else { "another footnote" };
+            return Err(CompileError::syntax(format!("a footnote cannot appear inside {}", inside))
+                .with_span(start)
+                .in_file(self.source_name));
+        }
+        self.in_footnote = true;
+        let (children, result) = self.parse_styled_body(opener, "#GIMMEH FUTNOTE");
+        self.in_footnote = false;
+        let number = self.doc.footnotes.len() + 1;
+        self.doc.footnotes.push(Footnote { number, children, span: self.span_since(start), file: None });
+        self.emit(NodeKind::FootnoteRef(number), start);
+        result
     }
 
     /* ===================== NEW: LIST / ITEM ===================== */
 
-    fn parse_list(&mut self) {
+    fn parse_list(&mut self) -> ParseResult {
         // We are after #MAEK; current token is LIST
-        self.eat(Token::List);
-        self.html.push("<ul>");
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::List)?;
+        self.open_frame();
+        let result = self.parse_list_body(opener, "#MAEK LIST");
+        let children = self.close_frame();
+        self.emit(NodeKind::List(children), start);
+        result
+    }
 
-        // Inside a list, expect zero or more "#GIMMEH ITEM ... #MKAY"
-        loop {
-            match self.look {
-                Token::HashGimmeh => {
-                    self.eat(Token::HashGimmeh);
-                    match self.look {
-                        Token::Item => self.parse_list_items(),
-                        Token::Newline => self.parse_newline(), // allow line breaks in list body
-                        _ => {
-                            eprintln!("Inside LIST: expected ITEM after #GIMMEH");
-                            std::process::exit(1);
-                        }
-                    }
-                }
-                Token::HashObtW => self.parse_comment(),
-                Token::HashOic => {
-                    self.eat(Token::HashOic);
-                    break;
-                }
-                Token::Eof => {
-                    eprintln!("Syntax error: unexpected EOF inside LIST");
-                    std::process::exit(1);
-                }
+    /// `#MAEK NUMBRD LIST [start] [decimal|roman|alpha] ... #OIC`. A lowercase
+    /// style word gives lowercase numerals (`roman` = i, ii), otherwise uppercase.
+    fn parse_numbered_list(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::Numbrd)?;
+        self.eat(Token::List)?;
+
+        let mut first = None;
+        let mut style = NumberStyle::Decimal;
+        for (word, span) in self.read_words()? {
+            let lower = word == word.to_ascii_lowercase();
+            match (word.parse::<u32>(), word.to_ascii_lowercase().as_str()) {
+                (Ok(n), _) if first.is_none() => first = Some(n),
+                (_, "decimal") => style = NumberStyle::Decimal,
+                (_, "roman") if lower => style = NumberStyle::LowerRoman,
+                (_, "roman") => style = NumberStyle::UpperRoman,
+                (_, "alpha") if lower => style = NumberStyle::LowerAlpha,
+                (_, "alpha") => style = NumberStyle::UpperAlpha,
                 _ => {
-                    // Be strict: only items/comments/newlines allowed inside a LIST
-                    eprintln!("Unexpected token inside LIST: {:?}", self.look);
-                    std::process::exit(1);
+                    self.report(CompileError::syntax(format!("unknown NUMBRD LIST option `{}`", word))
+                        .with_span(span)
+                        .in_file(self.source_name)
+                        .with_help("options are a start number and one of `decimal`, `roman`, `alpha`"))?;
                 }
             }
         }
 
-        self.html.push("</ul>");
+        self.open_frame();
+        let result = self.parse_list_body(opener, "#MAEK NUMBRD LIST");
+        let children = self.close_frame();
+        self.emit(NodeKind::NumberedList { start: first, style, children }, start);
+        result
     }
 
-    fn parse_list_items(&mut self) {
+    fn parse_list_items(&mut self) -> ParseResult {
         // Current token is ITEM
-        self.eat(Token::Item);
-        self.html.push("<li>");
-        self.parse_inner_list(); // parses until #MKAY, supporting rich inline content
-        self.html.push("</li>");
+        let start = self.tag_start;
+        self.eat(Token::Item)?;
+        self.open_frame();
+        let outer = std::mem::replace(&mut self.allow_blocks, true);
+        let result = self.parse_inner_list(); // parses until #MKAY, supporting rich inline content
+        self.allow_blocks = outer;
+        let children = self.close_frame();
+        self.emit(NodeKind::Item(children), start);
+        result
     }
 
-    fn parse_inner_list(&mut self) {
-        // Parse inline constructs until we hit #MKAY (end of ITEM)
-        self.parse_inline_until_mkay();
+    fn parse_inner_list(&mut self) -> ParseResult {
+        // Parse inline constructs until we hit #MKAY (end of ITEM); called
+        // right after ITEM, so `tag_start` is still its #GIMMEH
+        self.parse_inline_until_mkay(self.tag_start, "#GIMMEH ITEM")
     }
 
     /* ===================== NEW: AUDIO / VIDEO ===================== */
 
-    fn parse_audio(&mut self) {
-        self.eat(Token::Soundz);
-        let src = self.read_text_until_mkay();
-        let src = src.trim();
-        self.html.push(format!(
-            "<audio controls><source src=\"{}\" /></audio>",
-            html_escape(src)
-        ));
+    fn parse_audio(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Soundz)?;
+        let src = self.read_text_until_mkay()?;
+        self.emit(NodeKind::Audio(src.trim().to_string()), start);
+        Ok(())
+    }
+
+    fn parse_video(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Vidz)?;
+        let src = self.read_text_until_mkay()?;
+        self.emit(NodeKind::Video(src.trim().to_string()), start);
+        Ok(())
     }
 
-    fn parse_video(&mut self) {
-        self.eat(Token::Vidz);
-        let src = self.read_text_until_mkay();
-        let src = src.trim();
-        // Simple iframe; you can style/size later as needed
-        self.html.push(format!(
-            "<iframe src=\"{}\" allowfullscreen loading=\"lazy\"></iframe>",
-            html_escape(src)
-        ));
+    /// `#GIMMEH HTMLZ <markup> #MKAY`: the one place text skips escaping.
+    fn parse_raw(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Htmlz)?;
+        let markup = self.read_text_until_mkay()?;
+        self.emit(NodeKind::Raw(markup), start);
+        Ok(())
+    }
+
+    /// `#GIMMEH HEADR <1-6> <text> #MKAY`
+    fn parse_heading(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Headr)?;
+        let level_span = self.span;
+        let t = self.read_text_until_mkay()?;
+        let (level, text) = t.split_once(char::is_whitespace).unwrap_or((t.as_str(), ""));
+        let Some(level) = level.parse::<u8>().ok().filter(|l| (1..=6).contains(l)) else {
+            // The whole construct was consumed, so record without resynchronizing.
+            return self.report(CompileError::syntax(format!("heading level must be 1-6, found `{}`", level))
+                .with_span(level_span)
+                .in_file(self.source_name)
+                .with_label("expected a level from 1 to 6")
+                .with_help("write the level first, e.g. `#GIMMEH HEADR 2 Section name #MKAY`"));
+        };
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return self.report(CompileError::syntax("heading has no text")
+                .with_span(self.span_since(start))
+                .in_file(self.source_name)
+                .with_label("expected text after the level")
+                .with_help(format!("write the text after the level, e.g. `#GIMMEH HEADR {} Section name #MKAY`", level)));
+        }
+        self.emit(NodeKind::Heading { level, text, id: None }, start);
+        Ok(())
     }
 
-    fn parse_newline(&mut self) {
-        self.eat(Token::Newline);
-        self.eat(Token::HashMKay);
-        self.html.br();
+    /// `#GIMMEH LINKZ <url> [#IT IZ <rich text>] #MKAY`; without `#IT IZ`
+    /// the URL doubles as the link text.
+    fn parse_link(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Linkz)?;
+        if self.in_link {
+            return Err(CompileError::syntax("links cannot be nested")
+                .with_span(start)
+                .in_file(self.source_name)
+                .with_label("link inside another link's text"));
+        }
+
+        // The lexer breaks URLs at punctuation; URLs have no spaces, so the URL is one word.
+        let mut words = self.read_words()?.into_iter();
+        let Some((href, _)) = words.next() else {
+            return Err(self.syntax_error(format!("expected a URL after `#GIMMEH LINKZ`, found {}", self.look))
+                .with_label("expected a URL"));
+        };
+        if let Some((word, span)) = words.next() {
+            let rest: Vec<String> = std::iter::once(word.clone()).chain(words.map(|(w, _)| w)).collect();
+            return Err(CompileError::syntax(format!("unexpected `{}` after the link URL", word))
+                .with_span(span)
+                .in_file(self.source_name)
+                .with_label("URLs cannot contain spaces")
+                .with_help(format!("write the link text after `#IT IZ`, e.g. `#GIMMEH LINKZ {} #IT IZ {} #MKAY`", href, rest.join(" "))));
+        }
+
+        self.open_frame();
+        let result = if matches!(self.look, Token::HashItIz) {
+            self.in_link = true;
+            let outer = std::mem::replace(&mut self.allow_blocks, false);
+            let result = self.eat(Token::HashItIz).and_then(|_| self.parse_inline_until_mkay(start, "#GIMMEH LINKZ"));
+            self.allow_blocks = outer;
+            self.in_link = false;
+            result
+        } else {
+            self.eat(Token::HashMKay)
+        };
+        let mut children = self.close_frame();
+        if children.is_empty() {
+            children.push(Node { kind: NodeKind::Text(href.clone()), span: start, anchor: None });
+        }
+        self.emit(NodeKind::Link { href, children }, start);
+        result
     }
 
-    fn parse_text(&mut self) {
+    /// `#GIMMEH PIKCHUR <src> [<width>x<height>] #IT IZ <alt text> #MKAY`;
+    /// either dimension may be left out (`320x`, `x200`, or just `320`).
+    fn parse_image(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Pikchur)?;
+        let args_span = self.span;
+        let args = self.read_spaced_text()?;
+        let mut words = args.split_whitespace();
+        let Some(src) = words.next().map(str::to_string) else {
+            return Err(self.syntax_error(format!("expected an image URL after `#GIMMEH PIKCHUR`, found {}", self.look))
+                .with_label("expected a URL"));
+        };
+        let size = words.next();
+        let (width, height) = match size.map(parse_size) {
+            None => (None, None),
+            Some(Some(dims)) => dims,
+            Some(None) => {
+                return Err(CompileError::syntax(format!("invalid image size `{}`", size.unwrap_or("")))
+                    .with_span(args_span)
+                    .in_file(self.source_name)
+                    .with_help("write the size as `<width>x<height>`, e.g. `320x200`, `320x` or `x200`"));
+            }
+        };
+        if let Some(extra) = words.next() {
+            return Err(CompileError::syntax(format!("unexpected `{}` after image size", extra))
+                .with_span(args_span)
+                .in_file(self.source_name)
+                .with_help("put the description after `#IT IZ`"));
+        }
+
+        let alt = if matches!(self.look, Token::HashItIz) {
+            self.eat(Token::HashItIz)?;
+            self.read_text_until_mkay()?
+        } else {
+            self.eat(Token::HashMKay)?;
+            String::new()
+        };
+        if alt.trim().is_empty() {
+            // The whole construct was consumed, so record without resynchronizing.
+            return self.report(CompileError::syntax("image is missing alt text")
+                .with_span(self.span_since(start))
+                .in_file(self.source_name)
+                .with_label("no description for this image")
+                .with_help(format!("describe it: `#GIMMEH PIKCHUR {} #IT IZ <description> #MKAY`", src)));
+        }
+        self.emit(NodeKind::Image { src, alt: alt.trim().to_string(), width, height }, start);
+        Ok(())
+    }
+
+    /// `#MAEK TABL` followed by rows, `#MAEK HEADR ROW` rows first.
+    fn parse_table(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::Tabl)?;
+        self.open_frame();
+        let result = self.parse_table_body(opener);
+        let rows = self.close_frame();
+        self.emit(NodeKind::Table(rows), start);
+        result
+    }
+
+    /// `#MAEK KODE [lang]`, then every following line verbatim until a
+    /// line starting with `#OIC`. The lexer has stopped right after `KODE`,
+    /// so the block is read from it directly instead of through tokens.
+    fn parse_code_block(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        let (lang, lang_span) = self.lexer.read_line_rest();
+        // Checked before the body is read, so errors stay in source order.
+        if lang.split_whitespace().nth(1).is_some() {
+            self.report(CompileError::syntax(format!("expected one language name after `KODE`, found `{}`", lang))
+                .with_span(lang_span)
+                .in_file(self.source_name)
+                .with_help("start the code itself on the next line"))?;
+        }
+        let code = self.lexer.read_verbatim("#OIC");
+        self.span.end = self.lexer.here().start;
+        let closed = code.is_some();
+        if !closed {
+            self.report(CompileError::syntax("unexpected end of input inside `#MAEK KODE`")
+                .with_span(self.lexer.here())
+                .in_file(self.source_name)
+                .with_related(opener, "`#MAEK KODE` opened here")
+                .with_help("close the code block with `#OIC` at the start of its own line"))?;
+        }
+        self.bump()?;
+        if closed {
+            let lang = (!lang.is_empty()).then_some(lang);
+            self.emit(NodeKind::CodeBlock { lang, code: code.unwrap_or_default() }, start);
+        }
+        Ok(())
+    }
+
+    fn parse_table_row(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let header = matches!(self.look, Token::Headr);
+        if header {
+            self.eat(Token::Headr)?;
+        }
+        let opener = self.span;
+        self.eat(Token::Row)?;
+        let name = if header { "#MAEK HEADR ROW" } else { "#MAEK ROW" };
+        self.open_frame();
+        let result = self.parse_row_body(opener, name);
+        let cells = self.close_frame();
+        self.emit(NodeKind::Row { header, cells }, start);
+        result
+    }
+
+    fn parse_table_cell(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        let opener = self.span;
+        self.eat(Token::Cell)?;
+        self.open_frame();
+        self.in_cell = true;
+        let result = self.parse_inline_until_mkay(opener, "#GIMMEH CELL");
+        self.in_cell = false;
+        let children = self.close_frame();
+        self.emit(NodeKind::Cell(children), start);
+        result
+    }
+
+    fn parse_newline(&mut self) -> ParseResult {
+        let start = self.tag_start;
+        self.eat(Token::Newline)?;
+        self.eat(Token::HashMKay)?;
+        self.emit(NodeKind::Newline, start);
+        Ok(())
+    }
+
+    fn parse_text(&mut self) -> ParseResult {
         if let Token::Text(t) = self.look.clone() {
-            self.eat(Token::Text(t.clone()));
-            self.html.text(&t);
+            let start = self.span;
+            self.eat(Token::Text(t.clone()))?;
+            self.emit(NodeKind::Text(t), start);
+            Ok(())
         } else {
-            eprintln!("Internal: parse_text called on non-text");
-            std::process::exit(1);
+            Err(self.syntax_error("internal: parse_text called on non-text"))
         }
     }
 }
 
-/* ---------- tiny HTML escaper for attributes ---------- */
-fn html_escape(s: &str) -> String {
-    s.chars()
-        .map(|c| match c {
-            '&' => "&amp;".to_string(),
-            '<' => "&lt;".to_string(),
-            '>' => "&gt;".to_string(),
-            '"' => "&quot;".to_string(),
-            '\'' => "&#39;".to_string(),
-            _ => c.to_string(),
-        })
-        .collect::<String>()
+/// `320x200`, `320x`, `x200` or `320` into optional width/height.
+fn parse_size(s: &str) -> Option<(Option<u32>, Option<u32>)> {
+    let dim = |d: &str| if d.is_empty() { Ok(None) } else { d.parse::<u32>().map(Some) };
+    let (w, h) = s.split_once(['x', 'X']).unwrap_or((s, ""));
+    match (dim(w), dim(h)) {
+        (Ok(None), Ok(None)) => None,
+        (Ok(w), Ok(h)) => Some((w, h)),
+        _ => None,
+    }
 }
 
 pub struct FrontEnd<'a> {
@@ -450,11 +1514,39 @@ pub struct FrontEnd<'a> {
 }
 
 impl<'a> FrontEnd<'a> {
-    pub fn new(input: &str, source_name: &'a str) -> Self {
-        Self { parser: Parser::new(input, source_name) }
+    pub fn new(input: &str, source_name: &'a str, error_limit: Option<usize>) -> Self {
+        Self { parser: Parser::new(input, source_name).with_error_limit(error_limit) }
     }
-    pub fn run(mut self) -> String {
-        self.parser.parse_lolcode();
-        self.parser.into_html()
+
+    /// Let `#GIMMEH FILEZ` read files under `root` (`None` = includes are an error).
+    pub fn with_include_root(mut self, root: Option<PathBuf>) -> Self {
+        self.parser = self.parser.with_include_root(root);
+        self
+    }
+    /// Parse into a document tree, or return every error found.
+    pub fn parse(mut self) -> Result<Document, Failure> {
+        if let Err(e) = self.parser.parse_lolcode() {
+            // Errors that unwound to the top were not recorded yet (and are
+            // dropped if the error limit was already reached).
+            let _ = self.parser.report(e);
+        }
+        if self.parser.errors.is_empty() {
+            // Second pass: needs the whole tree, so it only runs on a clean parse.
+            let source_name = self.parser.source_name;
+            let limit = self.parser.error_limit;
+            let mut doc = self.parser.into_document();
+            let mut errors = outline::resolve(&mut doc, source_name);
+            let aborted = limit.is_some_and(|limit| errors.len() > limit);
+            if let Some(limit) = limit {
+                errors.truncate(limit);
+            }
+            if errors.is_empty() { Ok(doc) } else { Err(Failure { errors, aborted }) }
+        } else {
+            Err(Failure { errors: self.parser.errors, aborted: self.parser.aborted })
+        }
+    }
+
+    pub fn run(self) -> Result<String, Failure> {
+        self.parse().map(|doc| htmlgen::render(&doc))
     }
 }
@@ -0,0 +1,180 @@
+//! Golden-file tests: compile every fixture and compare against the checked-in
+//! expectation next to it.
+//!
+//! - `Name.lol` that compiles must match `Name.html` byte for byte.
+//! - `Name.lol` that fails must match `Name.err`, one reported error per line
+//!   (`file:line:col: <Kind> error: message`), which pins the error kinds.
+//! - If `Name.stderr` exists, it must also match the errors as the CLI
+//!   renders them (snippets, carets, help lines), without colour. Create an
+//!   empty one and bless to start checking a fixture this way.
+//!
+//! Fixtures are the professor-provided `Test*.lol` files in the crate root plus
+//! anything in `tests/fixtures/`; files that fixtures include with
+//! `#GIMMEH FILEZ` live in subdirectories there and are not compiled alone.
+//! Set `LOLCOMPILER_BLESS=1` to rewrite the expectations from the current
+//! compiler output after an intentional change.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use lolcompiler::{compile, CompileOptions};
+
+const BLESS_VAR: &str = "LOLCOMPILER_BLESS";
+
+fn fixtures() -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut found = Vec::new();
+    for dir in [root.to_path_buf(), root.join("tests").join("fixtures")] {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let in_root = dir == root;
+            if name.ends_with(".lol") && (!in_root || name.starts_with("Test")) {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Compile one fixture into (expected file extension, actual contents,
+/// rendered errors).
+fn run(path: &Path) -> (&'static str, String, String) {
+    let source = fs::read_to_string(path).expect("fixture is readable");
+    // Relative to the crate so `#GIMMEH FILEZ` paths resolve from the fixture's
+    // directory, with `/` so expectations match on every platform.
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let name = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let options = CompileOptions {
+        source_name: name.clone(),
+        include_root: path.parent().map(Path::to_path_buf),
+        ..Default::default()
+    };
+    match compile(&source, &options) {
+        Ok(html) => ("html", html, String::new()),
+        Err(errors) => (
+            "err",
+            errors.iter().map(|e| format!("{}\n", e)).collect(),
+            // As main.rs prints them: one blank line after each, and errors
+            // from `#GIMMEH FILEZ` shown against the included file.
+            errors
+                .iter()
+                .map(|e| {
+                    let text = match &e.file {
+                        Some(file) if *file != name => fs::read_to_string(root.join(file)).unwrap_or_default(),
+                        _ => source.clone(),
+                    };
+                    format!("{}\n", e.render(&text, false))
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Short description of where two outputs first differ.
+fn first_difference(expected: &str, actual: &str) -> String {
+    for (i, (e, a)) in expected.lines().zip(actual.lines()).enumerate() {
+        if e != a {
+            return format!("line {}:\n    expected: {}\n    actual:   {}", i + 1, e, a);
+        }
+    }
+    format!(
+        "outputs agree for {} lines but differ in length ({} vs {} bytes)",
+        expected.lines().count().min(actual.lines().count()),
+        expected.len(),
+        actual.len()
+    )
+}
+
+#[test]
+fn fixtures_match_expected_output() {
+    let bless = std::env::var_os(BLESS_VAR).is_some();
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty(), "no .lol fixtures found");
+
+    let mut failures = Vec::new();
+    for path in &fixtures {
+        let (ext, actual, rendered) = run(path);
+        let expected_path = path.with_extension(ext);
+        let other_path = path.with_extension(if ext == "html" { "err" } else { "html" });
+        let stderr_path = path.with_extension("stderr");
+
+        if bless {
+            fs::write(&expected_path, &actual).expect("write expectation");
+            let _ = fs::remove_file(&other_path);
+            if stderr_path.exists() {
+                fs::write(&stderr_path, &rendered).expect("write expectation");
+            }
+            continue;
+        }
+
+        if let Ok(expected) = fs::read_to_string(&stderr_path)
+            && expected != rendered
+        {
+            failures.push(format!(
+                "{}: rendered errors differ from {}, {}",
+                path.display(),
+                stderr_path.display(),
+                first_difference(&expected, &rendered)
+            ));
+        }
+
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}: output differs from {}, {}",
+                path.display(),
+                expected_path.display(),
+                first_difference(&expected, &actual)
+            )),
+            Err(_) if other_path.exists() => failures.push(format!(
+                "{}: expected {} but compilation produced .{} output:\n{}",
+                path.display(),
+                other_path.display(),
+                ext,
+                actual
+            )),
+            Err(_) => failures.push(format!(
+                "{}: no {} to compare against (run with {}=1 to create it)",
+                path.display(),
+                expected_path.display(),
+                BLESS_VAR
+            )),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} fixtures failed (re-run with {}=1 if the change is intentional):\n\n{}",
+        failures.len(),
+        fixtures.len(),
+        BLESS_VAR,
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn rendered_errors_are_coloured_only_on_request() {
+    let source = "#HAI\n#MAEK HEAD #GIMMEH TITLE Colour #MKAY #OIC\n#MAEK PARAGRAF #LEMME SEE nope #MKAY #OIC\n#KTHXBYE\n";
+    let options = CompileOptions { source_name: "colour.lol".into(), ..Default::default() };
+    let errors = compile(source, &options).unwrap_err();
+    let plain = errors[0].render(source, false);
+    let coloured = errors[0].render(source, true);
+    assert!(!plain.contains('\x1b'), "{}", plain);
+    assert!(coloured.contains("\x1b[1;31m"), "{}", coloured);
+    // Same text once the escape codes are gone.
+    let stripped = coloured
+        .split('\x1b')
+        .enumerate()
+        .map(|(i, piece)| if i == 0 { piece } else { piece.split_once('m').map_or(piece, |(_, rest)| rest) })
+        .collect::<String>();
+    assert_eq!(stripped, plain);
+}
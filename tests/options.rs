@@ -0,0 +1,65 @@
+//! `CompileOptions` knobs that the golden fixtures, which all compile with
+//! the same options, cannot show.
+
+use std::path::Path;
+
+use lolcompiler::{compile, compile_with_status, CompileOptions, ErrorKind};
+
+// Four independent errors, one per paragraph.
+const FOUR_ERRORS: &str = "#HAI\n\
+    #MAEK HEAD #GIMMEH TITLE Limits #MKAY #OIC\n\
+    #MAEK PARAGRAF #LEMME SEE a #MKAY #OIC\n\
+    #MAEK PARAGRAF #LEMME SEE b #MKAY #OIC\n\
+    #MAEK PARAGRAF #LEMME SEE c #MKAY #OIC\n\
+    #MAEK PARAGRAF #LEMME SEE d #MKAY #OIC\n\
+    #KTHXBYE\n";
+
+const INCLUDING: &str = "#HAI\n#GIMMEH FILEZ includes/header.lol #MKAY\n#KTHXBYE\n";
+
+fn fixtures_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+}
+
+#[test]
+fn includes_are_off_by_default() {
+    let options = CompileOptions { source_name: "tests/fixtures/page.lol".into(), ..Default::default() };
+    let errors = compile(INCLUDING, &options).unwrap_err();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].kind, ErrorKind::Semantic);
+    assert!(errors[0].message.contains("not enabled"), "{}", errors[0]);
+}
+
+#[test]
+fn includes_stay_inside_the_include_root() {
+    let options = CompileOptions {
+        source_name: "tests/fixtures/page.lol".into(),
+        include_root: Some(fixtures_dir().join("includes").join("parts")),
+        ..Default::default()
+    };
+    let errors = compile(INCLUDING, &options).unwrap_err();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(errors[0].message.contains("outside the include root"), "{}", errors[0]);
+
+    let options = CompileOptions { include_root: Some(fixtures_dir().to_path_buf()), ..options };
+    let html = compile(INCLUDING, &options).expect("include under the root compiles");
+    assert!(html.contains("Field Guide"));
+}
+
+#[test]
+fn error_limit_caps_the_report() {
+    let unlimited = compile(FOUR_ERRORS, &CompileOptions::default()).unwrap_err();
+    assert_eq!(unlimited.len(), 4);
+
+    for limit in 1..=3 {
+        let options = CompileOptions { error_limit: Some(limit), ..Default::default() };
+        let failure = compile_with_status(FOUR_ERRORS, &options).unwrap_err();
+        assert_eq!(failure.errors, unlimited[..limit], "limit {}", limit);
+        assert!(failure.aborted, "limit {} should stop early", limit);
+    }
+
+    // Exactly as many errors as the limit: everything was reported.
+    let options = CompileOptions { error_limit: Some(4), ..Default::default() };
+    let failure = compile_with_status(FOUR_ERRORS, &options).unwrap_err();
+    assert_eq!(failure.errors, unlimited);
+    assert!(!failure.aborted);
+}
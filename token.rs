@@ -14,11 +14,34 @@ pub enum Token {
     Paragraf,
     Bold,
     Italics,
+    Underlin,     // underline
+    Strike,       // strikethrough
+    Supr,         // superscript
+    Subz,         // subscript
+    Hilite,       // highlight
     List,
+    Numbrd,       // NUMBRD LIST (ordered)
     Item,
     Newline,
     Soundz,
     Vidz,
+    Htmlz,        // raw HTML passthrough
+    Headr,        // heading, level 1-6
+    Linkz,        // hyperlink
+    Pikchur,      // image
+    Tabl,         // table
+    Row,          // table row (HEADR ROW for a header row)
+    Cell,         // table cell
+    Kode,         // verbatim code block / inline code
+    Quotez,       // blockquote
+    Cite,         // blockquote attribution
+    Lyne,         // horizontal rule
+    Secshun,      // <section>
+    Futnote,      // footnote
+    Toc,          // table of contents
+    Ankr,         // anchor for the next block
+    Refz,         // cross-reference to an anchor
+    Filez,        // include another .lol file
     HashIHaz,     // #I HAZ
     HashItIz,     // #IT IZ
     HashLemmeSee, // #LEMME SEE
@@ -36,3 +59,75 @@ impl Token {
         matches!(self, Token::Text(_))
     }
 }
+
+/// Tokens print the way they are spelled in `.lol` source, for diagnostics.
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Token::HashHai => "#HAI",
+            Token::HashKthxbye => "#KTHXBYE",
+            Token::HashObtW => "#OBTW",
+            Token::HashTldr => "#TLDR",
+            Token::HashMaek => "#MAEK",
+            Token::HashOic => "#OIC",
+            Token::HashGimmeh => "#GIMMEH",
+            Token::HashMKay => "#MKAY",
+            Token::Head => "HEAD",
+            Token::Title => "TITLE",
+            Token::Paragraf => "PARAGRAF",
+            Token::Bold => "BOLD",
+            Token::Italics => "ITALICS",
+            Token::Underlin => "UNDERLIN",
+            Token::Strike => "STRIKE",
+            Token::Supr => "SUPR",
+            Token::Subz => "SUBZ",
+            Token::Hilite => "HILITE",
+            Token::List => "LIST",
+            Token::Numbrd => "NUMBRD",
+            Token::Item => "ITEM",
+            Token::Newline => "NEWLINE",
+            Token::Soundz => "SOUNDZ",
+            Token::Vidz => "VIDZ",
+            Token::Htmlz => "HTMLZ",
+            Token::Headr => "HEADR",
+            Token::Linkz => "LINKZ",
+            Token::Pikchur => "PIKCHUR",
+            Token::Tabl => "TABL",
+            Token::Row => "ROW",
+            Token::Cell => "CELL",
+            Token::Kode => "KODE",
+            Token::Quotez => "QUOTEZ",
+            Token::Cite => "CITE",
+            Token::Lyne => "LYNE",
+            Token::Secshun => "SECSHUN",
+            Token::Futnote => "FUTNOTE",
+            Token::Toc => "TOC",
+            Token::Ankr => "ANKR",
+            Token::Refz => "REFZ",
+            Token::Filez => "FILEZ",
+            Token::HashIHaz => "#I HAZ",
+            Token::HashItIz => "#IT IZ",
+            Token::HashLemmeSee => "#LEMME SEE",
+            Token::Text(t) => return write!(f, "text `{}`", t),
+            Token::Eof => return f.write_str("end of input"),
+        };
+        write!(f, "`{}`", s)
+    }
+}
+
+/// Where a lexeme sits in the source: a byte range plus the 1-based
+/// line/column of its first character.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A token together with the span it was lexed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}